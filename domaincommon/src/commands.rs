@@ -1,4 +1,4 @@
-use crate::{GridDirection, Point, RegisterOperation};
+use crate::{GridDirection, Point, RegisterOperation, Ruleset, WeaponType};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MechCommand {
@@ -7,6 +7,13 @@ pub enum MechCommand {
         mech: String,
         direction: GridDirection,
     },
+    /// Moves the mech one cell at a time along the shortest path to the target, for as
+    /// long as it has action points left. The mech stops early if it collides with anything
+    MoveTo {
+        turn: u32,
+        mech: String,
+        target: Point,
+    },
     FirePrimary {
         turn: u32,
         mech: String,
@@ -17,9 +24,23 @@ pub enum MechCommand {
         mech: String,
         direction: GridDirection,
     },
+    /// Turns the mech to face the given direction. Only useful in matches where
+    /// mechs have a facing
+    Rotate {
+        turn: u32,
+        mech: String,
+        direction: GridDirection,
+    },
+    /// Raises the mech's shield, absorbing part of the damage it takes until its next turn
+    RaiseShield { turn: u32, mech: String },
+    /// Braces the mech so it takes no collision damage until its next turn
+    Brace { turn: u32, mech: String },
     RequestRadarScan {
         turn: u32,
         mech: String,
+        /// The direction to aim the scan, only used by cone-shaped radar patterns
+        #[serde(default)]
+        direction: Option<GridDirection>,
     },
     SpawnMech {
         mech: String,
@@ -44,16 +65,80 @@ pub enum MechCommand {
     },
 }
 
+/// A command the arena refused to carry out, and why
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandRejection {
+    pub turn: u32,
+    pub cmd: MechCommand,
+    pub reason: String,
+}
+
 impl MechCommand {
-    pub fn action_points(&self) -> u32 {
+    pub fn action_points(&self, rules: &Ruleset) -> u32 {
         match self {
-            MechCommand::Move { .. } => 1,
-            MechCommand::FirePrimary { .. } => 2,
-            MechCommand::FireSecondary { .. } => 4,
-            MechCommand::RequestRadarScan { .. } => 1,
+            MechCommand::Move { .. } => rules.move_cost,
+            MechCommand::MoveTo { .. } => rules.move_cost,
+            MechCommand::FirePrimary { .. } => rules.primary_cost,
+            MechCommand::FireSecondary { .. } => rules.secondary_cost,
+            MechCommand::RequestRadarScan { .. } => rules.radar_cost,
+            MechCommand::Rotate { .. } => rules.rotate_cost,
+            MechCommand::RaiseShield { .. } => rules.shield_cost,
+            MechCommand::Brace { .. } => rules.brace_cost,
             MechCommand::SpawnMech { .. } => 0,
             MechCommand::FinishTurn { .. } => 0,
             MechCommand::RegisterUpdate { .. } => 0,
         }
     }
+
+    /// The mech issuing this command
+    pub fn mech(&self) -> &str {
+        match self {
+            MechCommand::Move { mech, .. }
+            | MechCommand::MoveTo { mech, .. }
+            | MechCommand::FirePrimary { mech, .. }
+            | MechCommand::FireSecondary { mech, .. }
+            | MechCommand::Rotate { mech, .. }
+            | MechCommand::RaiseShield { mech, .. }
+            | MechCommand::Brace { mech, .. }
+            | MechCommand::RequestRadarScan { mech, .. }
+            | MechCommand::SpawnMech { mech, .. }
+            | MechCommand::FinishTurn { mech, .. }
+            | MechCommand::RegisterUpdate { mech, .. } => mech,
+        }
+    }
+
+    /// The turn for which this command was issued. Spawning happens before the first turn
+    pub fn turn(&self) -> Option<u32> {
+        match self {
+            MechCommand::Move { turn, .. }
+            | MechCommand::MoveTo { turn, .. }
+            | MechCommand::FirePrimary { turn, .. }
+            | MechCommand::FireSecondary { turn, .. }
+            | MechCommand::Rotate { turn, .. }
+            | MechCommand::RaiseShield { turn, .. }
+            | MechCommand::Brace { turn, .. }
+            | MechCommand::RequestRadarScan { turn, .. }
+            | MechCommand::FinishTurn { turn, .. }
+            | MechCommand::RegisterUpdate { turn, .. } => Some(*turn),
+            MechCommand::SpawnMech { .. } => None,
+        }
+    }
+
+    /// The direction in which this command aims the mech's weapons, if any
+    pub fn aim(&self) -> Option<GridDirection> {
+        match self {
+            MechCommand::FirePrimary { direction, .. }
+            | MechCommand::FireSecondary { direction, .. } => Some(*direction),
+            _ => None,
+        }
+    }
+
+    /// The weapon fired by this command, if any
+    pub fn weapon(&self) -> Option<WeaponType> {
+        match self {
+            MechCommand::FirePrimary { .. } => Some(WeaponType::Primary),
+            MechCommand::FireSecondary { .. } => Some(WeaponType::Secondary),
+            _ => None,
+        }
+    }
 }
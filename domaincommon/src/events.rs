@@ -1,10 +1,45 @@
 use crate::commands::MechCommand;
-use crate::{DamageSource, Point, RadarPing, RegisterValue, DOMAIN_VERSION};
+use crate::{
+    CommandViolation, DamageSource, GridDirection, Penalty, Pickup, Point, RadarPing,
+    RegisterFault, RegisterOperation, RegisterValue, TurnOrder, WeaponType, WeaponUnavailable,
+    DOMAIN_VERSION,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum EndCause {
-    MaxTurnsCompleted { survivors: Vec<String> },
+    MaxTurnsCompleted {
+        survivors: Vec<String>,
+    },
     MechVictory(String),
+    /// The last mechs standing were destroyed at the same time
+    Draw {
+        mechs: Vec<String>,
+    },
+    /// Only mechs from a single team are left standing in a team match
+    TeamVictory {
+        team: String,
+        survivors: Vec<String>,
+        /// Every mech on the defeated teams, destroyed or not
+        #[serde(default)]
+        losers: Vec<String>,
+    },
+    /// The turn limit was reached in a team match with more than one team still standing
+    TeamDraw {
+        teams: Vec<String>,
+        survivors: Vec<String>,
+    },
+    /// Every mech still in play lost its connection to the arena
+    AllMechsDisconnected,
+    /// An administrator stopped the match
+    Aborted {
+        reason: String,
+    },
+    /// The victor was the last mech left in play after the others disconnected, were
+    /// disqualified, or were ejected
+    Forfeit {
+        victor: String,
+        forfeited: Vec<String>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Event)]
@@ -23,6 +58,9 @@ pub enum GameEvent {
     MechDestroyed {
         damage_target: String,
         damage_source: DamageSource,
+        /// Whether the mech was destroyed by a teammate's weapon
+        #[serde(default)]
+        friendly_fire: bool,
     },
     MechSpawned {
         mech: String,
@@ -31,6 +69,26 @@ pub enum GameEvent {
         avatar: String,
         name: String,
     },
+    FacingChanged {
+        mech: String,
+        facing: GridDirection,
+    },
+    ShieldRaised {
+        mech: String,
+    },
+    Braced {
+        mech: String,
+    },
+    /// The mech's shield and brace dropped as its next turn began
+    DefensesLowered {
+        mech: String,
+    },
+    /// Damage that a mech's shield or brace kept it from taking
+    DamageBlocked {
+        mech: String,
+        damage: u32,
+        damage_source: DamageSource,
+    },
     RadarScanCompleted {
         actor: String,
         results: Vec<RadarPing>,
@@ -43,6 +101,37 @@ pub enum GameEvent {
         mech: String,
         cmd: MechCommand,
     },
+    /// The mech didn't respond within the match's time limit and forfeited its turn
+    TurnTimedOut {
+        mech: String,
+        turn: u32,
+    },
+    /// A command that couldn't be carried out, such as one for an unknown mech
+    CommandRejected {
+        mech: String,
+        cmd: MechCommand,
+        reason: String,
+    },
+    /// An actor returned a command it had no right to issue
+    CommandViolated {
+        actor: String,
+        cmd: MechCommand,
+        violation: CommandViolation,
+    },
+    WeaponFired {
+        mech: String,
+        weapon: WeaponType,
+    },
+    WeaponFireRejected {
+        mech: String,
+        weapon: WeaponType,
+        reason: WeaponUnavailable,
+    },
+    PenaltyApplied {
+        mech: String,
+        turn: u32,
+        penalty: Penalty,
+    },
     MechTurnCompleted {
         mech: String,
         turn: u32,
@@ -50,6 +139,11 @@ pub enum GameEvent {
     MatchTurnCompleted {
         new_turn: u32,
     },
+    TurnOrderDetermined {
+        turn: u32,
+        policy: TurnOrder,
+        order: Vec<String>,
+    },
     GameFinished {
         cause: EndCause,
     },
@@ -58,4 +152,31 @@ pub enum GameEvent {
         reg: u32,
         val: RegisterValue,
     },
+    RegisterCleared {
+        mech: String,
+        reg: u32,
+    },
+    /// A register operation that couldn't be applied, leaving the register unchanged
+    RegisterOperationRejected {
+        mech: String,
+        reg: u32,
+        op: RegisterOperation,
+        fault: RegisterFault,
+    },
+    TerrainDamaged {
+        position: Point,
+        damage: u32,
+        damage_source: DamageSource,
+    },
+    TerrainDestroyed {
+        position: Point,
+        damage_source: DamageSource,
+    },
+    PickupSpawned {
+        pickup: Pickup,
+    },
+    PickupCollected {
+        mech: String,
+        pickup: Pickup,
+    },
 }
@@ -15,8 +15,18 @@ pub struct PlayerStats {
     pub score: usize,
     pub wins: usize,
     pub draws: usize,
+    /// Matches lost as part of a defeated team
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub losses: usize,
     pub kills: usize,
     pub deaths: usize,
+    /// Damage kept out by shields and braces
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub damage_blocked: usize,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -31,6 +41,9 @@ pub struct MechSummary {
 pub struct LeaderboardData {
     pub stats: HashMap<String, PlayerStats>,
     pub mechs: HashMap<String, MechSummary>,
+    /// Results credited to teams for team matches
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub teams: HashMap<String, PlayerStats>,
     pub generation: u64,
 }
 
@@ -58,13 +71,46 @@ impl Aggregate for Leaderboard {
             GameEvent::MechDestroyed {
                 damage_target,
                 damage_source,
-            } => Self::score_mech_death(state, damage_target.to_string(), damage_source.clone()),
+                friendly_fire,
+            } => Self::score_mech_death(
+                state,
+                damage_target.to_string(),
+                damage_source.clone(),
+                *friendly_fire,
+            ),
+            GameEvent::DamageBlocked { mech, damage, .. } => {
+                Self::score_blocked_damage(state, mech.to_string(), *damage)
+            }
             GameEvent::GameFinished {
                 cause: EndCause::MechVictory(mech),
             } => Self::score_victory(state, mech.to_string()),
             GameEvent::GameFinished {
                 cause: EndCause::MaxTurnsCompleted { survivors },
             } => Self::score_draw(state, survivors.clone()),
+            GameEvent::GameFinished {
+                cause:
+                    EndCause::TeamVictory {
+                        team,
+                        survivors,
+                        losers,
+                    },
+            } => Self::score_team_victory(state, team.to_string(), survivors.clone())
+                .and_then(|state| Self::score_team_loss(&state, losers.clone())),
+            GameEvent::GameFinished {
+                cause: EndCause::TeamDraw { teams, survivors },
+            } => Self::score_team_draw(state, teams.clone(), survivors.clone()),
+            GameEvent::GameFinished {
+                cause: EndCause::Forfeit { victor, .. },
+            } => Self::score_victory(state, victor.to_string()),
+            GameEvent::GameFinished {
+                cause: EndCause::Draw { mechs },
+            } => Self::score_mutual_destruction(state, mechs.clone()),
+            GameEvent::GameFinished {
+                cause: EndCause::AllMechsDisconnected,
+            }
+            | GameEvent::GameFinished {
+                cause: EndCause::Aborted { .. },
+            } => Ok(state.clone()), // no contest
             _ => Ok(state.clone()),
         }
     }
@@ -79,26 +125,29 @@ impl Aggregate for Leaderboard {
 }
 
 impl Leaderboard {
-    // Source kills target
+    // Source kills target. Destroying a teammate earns nothing
     fn score_mech_death(
         state: &LeaderboardData,
         target: String,
         source: DamageSource,
+        friendly_fire: bool,
     ) -> eventsourcing::Result<LeaderboardData> {
         let mut state = state.clone();
         if let DamageSource::MechWeapon(attacker) = source {
-            state
-                .stats
-                .entry(attacker)
-                .and_modify(|e| {
-                    e.score += POINTS_DESTROY;
-                    e.kills += 1;
-                })
-                .or_insert(PlayerStats {
-                    score: POINTS_DESTROY,
-                    kills: 1,
-                    ..Default::default()
-                });
+            if !friendly_fire {
+                state
+                    .stats
+                    .entry(attacker)
+                    .and_modify(|e| {
+                        e.score += POINTS_DESTROY;
+                        e.kills += 1;
+                    })
+                    .or_insert(PlayerStats {
+                        score: POINTS_DESTROY,
+                        kills: 1,
+                        ..Default::default()
+                    });
+            }
             state
                 .stats
                 .entry(target)
@@ -115,6 +164,17 @@ impl Leaderboard {
         Ok(state)
     }
 
+    fn score_blocked_damage(
+        state: &LeaderboardData,
+        mech: String,
+        damage: u32,
+    ) -> eventsourcing::Result<LeaderboardData> {
+        let mut state = state.clone();
+        state.stats.entry(mech).or_default().damage_blocked += damage as usize;
+        state.generation += 1;
+        Ok(state)
+    }
+
     fn update_mech_map(
         state: &LeaderboardData,
         mech: &str,
@@ -159,6 +219,102 @@ impl Leaderboard {
         Ok(state)
     }
 
+    fn score_team_victory(
+        state: &LeaderboardData,
+        team: String,
+        survivors: Vec<String>,
+    ) -> eventsourcing::Result<LeaderboardData> {
+        let mut state = survivors
+            .into_iter()
+            .try_fold(state.clone(), |state, mech| {
+                Self::score_victory(&state, mech)
+            })?;
+
+        state
+            .teams
+            .entry(team)
+            .and_modify(|e| {
+                e.score += POINTS_MATCH_WIN;
+                e.wins += 1;
+            })
+            .or_insert(PlayerStats {
+                score: POINTS_MATCH_WIN,
+                wins: 1,
+                ..Default::default()
+            });
+
+        Ok(state)
+    }
+
+    // Every mech on a defeated team takes the loss, as does each of those teams
+    fn score_team_loss(
+        state: &LeaderboardData,
+        losers: Vec<String>,
+    ) -> eventsourcing::Result<LeaderboardData> {
+        let mut state = state.clone();
+
+        let mut teams: Vec<_> = losers
+            .iter()
+            .filter_map(|m| state.mechs.get(m))
+            .map(|m| m.team.to_string())
+            .collect();
+        teams.sort();
+        teams.dedup();
+        for loser in losers {
+            state.stats.entry(loser).or_default().losses += 1;
+        }
+        for team in teams {
+            state.teams.entry(team).or_default().losses += 1;
+        }
+        Ok(state)
+    }
+
+    fn score_team_draw(
+        state: &LeaderboardData,
+        teams: Vec<String>,
+        survivors: Vec<String>,
+    ) -> eventsourcing::Result<LeaderboardData> {
+        let mut state = Self::score_draw(state, survivors)?;
+
+        for team in teams {
+            state
+                .teams
+                .entry(team)
+                .and_modify(|e| {
+                    e.score += POINTS_MATCH_SURVIVE;
+                    e.draws += 1;
+                })
+                .or_insert(PlayerStats {
+                    score: POINTS_MATCH_SURVIVE,
+                    draws: 1,
+                    ..Default::default()
+                });
+        }
+        Ok(state)
+    }
+
+    // Mechs that destroy each other share the draw, but earn nothing for surviving
+    fn score_mutual_destruction(
+        state: &LeaderboardData,
+        mechs: Vec<String>,
+    ) -> eventsourcing::Result<LeaderboardData> {
+        let mut state = state.clone();
+
+        for mech in mechs {
+            state
+                .stats
+                .entry(mech)
+                .and_modify(|e| {
+                    e.draws += 1;
+                })
+                .or_insert(PlayerStats {
+                    draws: 1,
+                    ..Default::default()
+                });
+        }
+        Ok(state)
+    }
+
     fn score_draw(
         state: &LeaderboardData,
         survivors: Vec<String>,
@@ -194,10 +350,12 @@ mod test {
             GameEvent::MechDestroyed {
                 damage_source: DamageSource::MechWeapon("al".to_string()),
                 damage_target: "bob".to_string(),
+                friendly_fire: false,
             },
             GameEvent::MechDestroyed {
                 damage_source: DamageSource::MechWeapon("al".to_string()),
                 damage_target: "steve".to_string(),
+                friendly_fire: false,
             },
         ];
 
@@ -211,6 +369,19 @@ mod test {
         assert_eq!(state.stats["steve"].deaths, 1);
     }
 
+    #[test]
+    fn no_points_for_killing_teammates() {
+        let evt = GameEvent::MechDestroyed {
+            damage_source: DamageSource::MechWeapon("al".to_string()),
+            damage_target: "bob".to_string(),
+            friendly_fire: true,
+        };
+
+        let state = Leaderboard::apply_event(&LeaderboardData::default(), &evt).unwrap();
+        assert!(!state.stats.contains_key("al"));
+        assert_eq!(state.stats["bob"].deaths, 1);
+    }
+
     #[test]
     fn spawn_updates_map() {
         let evts = vec![
@@ -286,4 +457,135 @@ mod test {
         assert_eq!(state.stats["bob"].draws, 1);
         assert_eq!(state.stats["al"].wins, 1);
     }
+
+    #[test]
+    fn award_points_for_team_win() {
+        let evts = vec![GameEvent::GameFinished {
+            cause: EndCause::TeamVictory {
+                team: "red".to_string(),
+                survivors: vec!["al".to_string(), "bob".to_string()],
+                losers: vec![],
+            },
+        }];
+        let state = LeaderboardData::default();
+        let state = evts.iter().fold(state, |state, evt| {
+            Leaderboard::apply_event(&state, evt).unwrap()
+        });
+        assert_eq!(state.stats["al"].wins, 1);
+        assert_eq!(state.stats["bob"].score, POINTS_MATCH_WIN);
+        assert_eq!(state.teams["red"].wins, 1);
+        assert_eq!(state.teams["red"].score, POINTS_MATCH_WIN);
+    }
+
+    #[test]
+    fn team_losers_take_the_loss() {
+        let evts = vec![
+            spawned("al", "red"),
+            spawned("bob", "red"),
+            spawned("steve", "blue"),
+            spawned("jim", "blue"),
+            GameEvent::GameFinished {
+                cause: EndCause::TeamVictory {
+                    team: "red".to_string(),
+                    survivors: vec!["al".to_string()],
+                    losers: vec!["jim".to_string(), "steve".to_string()],
+                },
+            },
+        ];
+        let state = LeaderboardData::default();
+        let state = evts.iter().fold(state, |state, evt| {
+            Leaderboard::apply_event(&state, evt).unwrap()
+        });
+        assert_eq!(state.stats["al"].wins, 1);
+        assert!(!state.stats.contains_key("bob"));
+        assert_eq!(state.stats["steve"].losses, 1);
+        assert_eq!(state.stats["jim"].losses, 1);
+        assert_eq!(state.stats["jim"].score, 0);
+        assert_eq!(state.teams["red"].wins, 1);
+        assert_eq!(state.teams["blue"].losses, 1);
+        assert_eq!(state.teams["blue"].wins, 0);
+    }
+
+    #[test]
+    fn award_points_for_team_draw() {
+        let evts = vec![GameEvent::GameFinished {
+            cause: EndCause::TeamDraw {
+                teams: vec!["blue".to_string(), "red".to_string()],
+                survivors: vec!["al".to_string(), "steve".to_string()],
+            },
+        }];
+        let state = LeaderboardData::default();
+        let state = evts.iter().fold(state, |state, evt| {
+            Leaderboard::apply_event(&state, evt).unwrap()
+        });
+        assert_eq!(state.stats["al"].draws, 1);
+        assert_eq!(state.stats["steve"].score, POINTS_MATCH_SURVIVE);
+        assert_eq!(state.teams["red"].draws, 1);
+        assert_eq!(state.teams["blue"].score, POINTS_MATCH_SURVIVE);
+    }
+
+    fn spawned(mech: &str, team: &str) -> GameEvent {
+        GameEvent::MechSpawned {
+            avatar: "av".to_string(),
+            mech: mech.to_string(),
+            name: mech.to_string(),
+            team: team.to_string(),
+            position: Point::new(1, 1),
+        }
+    }
+
+    #[test]
+    fn score_other_endings() {
+        let evts = vec![
+            GameEvent::GameFinished {
+                cause: EndCause::Draw {
+                    mechs: vec!["al".to_string(), "bob".to_string()],
+                },
+            },
+            GameEvent::GameFinished {
+                cause: EndCause::Forfeit {
+                    victor: "al".to_string(),
+                    forfeited: vec!["bob".to_string()],
+                },
+            },
+            GameEvent::GameFinished {
+                cause: EndCause::Aborted {
+                    reason: "maintenance".to_string(),
+                },
+            },
+            GameEvent::GameFinished {
+                cause: EndCause::AllMechsDisconnected,
+            },
+        ];
+        let state = LeaderboardData::default();
+        let state = evts.iter().fold(state, |state, evt| {
+            Leaderboard::apply_event(&state, evt).unwrap()
+        });
+        assert_eq!(state.stats["al"].score, POINTS_MATCH_WIN);
+        assert_eq!(state.stats["al"].wins, 1);
+        assert_eq!(state.stats["al"].draws, 1);
+        assert_eq!(state.stats["bob"].score, 0);
+        assert_eq!(state.stats["bob"].draws, 1);
+    }
+
+    #[test]
+    fn count_blocked_damage() {
+        let evts = vec![
+            GameEvent::DamageBlocked {
+                mech: "al".to_string(),
+                damage: 50,
+                damage_source: DamageSource::MechWeapon("bob".to_string()),
+            },
+            GameEvent::DamageBlocked {
+                mech: "al".to_string(),
+                damage: 25,
+                damage_source: DamageSource::Wall,
+            },
+        ];
+        let state = evts.iter().fold(LeaderboardData::default(), |state, evt| {
+            Leaderboard::apply_event(&state, evt).unwrap()
+        });
+        assert_eq!(state.stats["al"].damage_blocked, 75);
+        assert_eq!(state.stats["al"].score, 0);
+    }
 }
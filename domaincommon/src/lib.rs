@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 #[macro_use]
 extern crate serde_derive;
@@ -7,13 +7,18 @@ extern crate serde_derive;
 extern crate eventsourcing_derive;
 pub extern crate eventsourcing;
 
-pub use radar::RadarPing;
+pub use pickups::{Pickup, PickupKind, PickupRules};
+pub use radar::{PingKind, RadarPattern, RadarPing};
+pub use rng::SeededRng;
 
 pub mod commands;
 pub mod events;
 pub mod leaderboard;
+mod pickups;
 mod radar;
+mod rng;
 pub mod state;
+pub mod turns;
 
 pub(crate) const DOMAIN_VERSION: &str = "1.0";
 
@@ -26,6 +31,23 @@ pub const EAX: u32 = 0;
 pub const ECX: u32 = 1;
 /// The base register
 pub const EBX: u32 = 2;
+/// The scratch memory register, which holds an array of bytes
+pub const MEM: u32 = 3;
+
+const NUMERIC_REGISTERS: u32 = 0x100;
+const TEXT_REGISTERS: u32 = 0x200;
+/// The most general purpose registers of each kind a register file can have
+pub const MAX_GENERAL_REGISTERS: u32 = TEXT_REGISTERS - NUMERIC_REGISTERS;
+
+/// Addresses the nth general purpose numeric register
+pub fn numeric_register(n: u32) -> u32 {
+    NUMERIC_REGISTERS.saturating_add(n)
+}
+
+/// Addresses the nth general purpose text register
+pub fn text_register(n: u32) -> u32 {
+    TEXT_REGISTERS.saturating_add(n)
+}
 
 /// A 2-dimensional coordinate within an arena
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -87,14 +109,26 @@ impl Point {
         points
     }
 
-    /// Returns a point 1 unit away in the direction indicated. Grid origin is the most Southwest point
+    /// Returns a point 1 unit away in the direction indicated. Grid origin is the most Southwest point.
+    /// Points that are off the board or blocked by solid terrain are not reachable
     pub fn relative_point(
         &self,
         board: &GameBoard,
         direction: &GridDirection,
         length: i32,
     ) -> Option<Point> {
-        let destination = match direction {
+        let destination = self.offset(direction, length);
+        if !destination.is_on_board(board) || board.is_blocked(&destination) {
+            None
+        } else {
+            Some(destination)
+        }
+    }
+
+    /// Returns the point the given distance away in the direction indicated, without regard
+    /// for the bounds or terrain of any board
+    pub fn offset(&self, direction: &GridDirection, length: i32) -> Point {
+        match direction {
             GridDirection::North => Point {
                 x: self.x,
                 y: self.y + length,
@@ -127,14 +161,34 @@ impl Point {
                 x: self.x - length,
                 y: self.y + length,
             },
-        };
-        if !destination.is_on_board(board) {
-            None
-        } else {
-            Some(destination)
         }
     }
 
+    /// Returns the points along a straight line from this point to the target, excluding
+    /// this point and ending with the target
+    pub fn line_to(&self, target: &Point) -> Vec<Point> {
+        let dx = (target.x - self.x).abs();
+        let dy = -(target.y - self.y).abs();
+        let sx = if self.x < target.x { 1 } else { -1 };
+        let sy = if self.y < target.y { 1 } else { -1 };
+        let mut err = dx + dy;
+        let (mut x, mut y) = (self.x, self.y);
+        let mut points = Vec::new();
+        while x != target.x || y != target.y {
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+            points.push(Point::new(x, y));
+        }
+        points
+    }
+
     pub fn is_on_board(&self, board: &GameBoard) -> bool {
         self.x <= board.width as _ && self.y <= board.height as _ && self.x >= 0 && self.y >= 0
     }
@@ -143,10 +197,12 @@ impl Point {
 /// Represents the dimensions and other metadata for a game board. All game boards
 /// have an origin of (0,0) that starts in the bottom left (southwest) corner of
 /// the scene.
-#[derive(Debug, Clone, Serialize, Deserialize, Copy)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameBoard {
     pub width: u32,
     pub height: u32,
+    #[serde(default)]
+    pub terrain: Vec<Obstacle>,
 }
 
 impl Default for GameBoard {
@@ -154,11 +210,104 @@ impl Default for GameBoard {
         GameBoard {
             width: DEFAULT_BOARD_WIDTH,
             height: DEFAULT_BOARD_HEIGHT,
+            terrain: Vec::new(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl GameBoard {
+    /// Returns the obstacle (if any) occupying the given point
+    pub fn obstacle_at(&self, position: &Point) -> Option<&Obstacle> {
+        self.terrain.iter().find(|o| o.position == *position)
+    }
+
+    /// Indicates whether the given point is occupied by terrain that stops mechs and projectiles
+    pub fn is_blocked(&self, position: &Point) -> bool {
+        self.obstacle_at(position)
+            .map(|o| o.terrain.is_solid())
+            .unwrap_or(false)
+    }
+
+    /// Finds the shortest series of single moves leading from one point to another that
+    /// steers clear of all terrain, including pits. Returns `None` if there is no such path
+    pub fn path(&self, from: &Point, to: &Point) -> Option<Vec<GridDirection>> {
+        if !to.is_on_board(self) || self.obstacle_at(to).is_some() {
+            return None;
+        }
+        let mut came_from: HashMap<(i32, i32), (Point, GridDirection)> = HashMap::new();
+        let mut frontier = VecDeque::new();
+        frontier.push_back(from.clone());
+        while let Some(p) = frontier.pop_front() {
+            if p == *to {
+                let mut path = Vec::new();
+                let mut current = p;
+                while current != *from {
+                    let (prev, dir) = came_from[&(current.x, current.y)].clone();
+                    path.push(dir);
+                    current = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            // Try the steps heading most directly at the target first, so paths run straight
+            let mut steps: Vec<_> = ALL_DIRECTIONS
+                .iter()
+                .map(|d| (*d, p.offset(d, 1)))
+                .collect();
+            steps.sort_by_key(|(_d, next)| (next.x - to.x).pow(2) + (next.y - to.y).pow(2));
+            for (dir, next) in steps {
+                if !next.is_on_board(self)
+                    || self.obstacle_at(&next).is_some()
+                    || next == *from
+                    || came_from.contains_key(&(next.x, next.y))
+                {
+                    continue;
+                }
+                came_from.insert((next.x, next.y), (p.clone(), dir));
+                frontier.push_back(next);
+            }
+        }
+        None
+    }
+}
+
+/// The kinds of terrain that can be placed on a game board
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum TerrainType {
+    /// An indestructible wall that stops mechs, projectiles, and radar
+    Wall,
+    /// Cover stops mechs, projectiles, and radar like a wall, but is destroyed
+    /// once it has absorbed its remaining health in weapon damage
+    Cover { health: u32 },
+    /// Projectiles and radar pass over pits, but a mech attempting to move into
+    /// one takes damage and stays where it is
+    Pit,
+}
+
+impl TerrainType {
+    /// Solid terrain blocks movement, projectiles, and radar
+    pub fn is_solid(&self) -> bool {
+        match self {
+            TerrainType::Wall | TerrainType::Cover { .. } => true,
+            TerrainType::Pit => false,
+        }
+    }
+}
+
+/// A single piece of terrain occupying a point on the game board
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Obstacle {
+    pub position: Point,
+    pub terrain: TerrainType,
+}
+
+impl Obstacle {
+    pub fn new(position: Point, terrain: TerrainType) -> Obstacle {
+        Obstacle { position, terrain }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum WeaponType {
     Primary = 0,
     Secondary = 1,
@@ -166,18 +315,128 @@ pub enum WeaponType {
 
 /// Represents a value contained within a register. Note that not all registers
 /// support all value types
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum RegisterValue {
     Number(u64),
     Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl RegisterValue {
+    pub fn kind(&self) -> RegisterKind {
+        match self {
+            RegisterValue::Number(_) => RegisterKind::Number,
+            RegisterValue::Text(_) => RegisterKind::Text,
+            RegisterValue::Bytes(_) => RegisterKind::Bytes,
+        }
+    }
+}
+
+/// The type of value a register holds
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum RegisterKind {
+    Number,
+    Text,
+    Bytes,
+}
+
+/// The registers available to each mech in a match. `EAX` and `ECX` are always numeric,
+/// `EBX` always holds text and `MEM` holds up to the memory quota of bytes. The general
+/// purpose registers are addressed with `numeric_register` and `text_register`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RegisterFile {
+    /// The number of general purpose numeric registers
+    pub numeric: u32,
+    /// The number of general purpose text registers
+    pub text: u32,
+    /// The most bytes that can be stored in the scratch memory register
+    pub memory_quota: usize,
+}
+
+impl Default for RegisterFile {
+    fn default() -> Self {
+        RegisterFile {
+            numeric: 16,
+            text: 4,
+            memory_quota: 256,
+        }
+    }
+}
+
+impl RegisterFile {
+    /// Checks that the register file fits in the register address space
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.numeric > MAX_GENERAL_REGISTERS || self.text > MAX_GENERAL_REGISTERS {
+            Err(format!(
+                "A register file can have at most {} numeric and {} text registers",
+                MAX_GENERAL_REGISTERS, MAX_GENERAL_REGISTERS
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The type of value the given register holds, if the register exists. Registers
+    /// beyond the limits of a register file that fails validation don't exist
+    pub fn kind(&self, reg: u32) -> Option<RegisterKind> {
+        let within = |base: u32, count: u32| {
+            reg.checked_sub(base)
+                .map_or(false, |n| n < count.min(MAX_GENERAL_REGISTERS))
+        };
+        match reg {
+            EAX | ECX => Some(RegisterKind::Number),
+            EBX => Some(RegisterKind::Text),
+            MEM => Some(RegisterKind::Bytes),
+            _ if within(NUMERIC_REGISTERS, self.numeric) => Some(RegisterKind::Number),
+            _ if within(TEXT_REGISTERS, self.text) => Some(RegisterKind::Text),
+            _ => None,
+        }
+    }
 }
 
-/// An operation performed on a register
+/// An operation performed on a register. Arithmetic on an empty numeric register treats
+/// its value as **0**
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RegisterOperation {
     Accumulate(u64),
     Decrement(u64),
     Set(RegisterValue),
+    /// Multiplies the register's value, saturating at the largest possible number
+    Multiply(u64),
+    /// Replaces the register's value with the remainder of dividing it by the given number
+    Modulo(u64),
+    /// Sets the register to `new` only if it currently holds `expected`
+    CompareAndSet {
+        expected: RegisterValue,
+        new: RegisterValue,
+    },
+    /// Copies the value of another register of the same kind into this one
+    Copy {
+        from: u32,
+    },
+    /// Exchanges the values of this register and another of the same kind
+    Swap {
+        with: u32,
+    },
+    /// Empties the register
+    Clear,
+}
+
+/// Why an operation left a register unchanged
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum RegisterFault {
+    /// The operation doesn't apply to a register holding this kind of value
+    WrongKind {
+        kind: RegisterKind,
+    },
+    /// The register being copied from is empty
+    Empty {
+        reg: u32,
+    },
+    DivisionByZero,
+    /// Compare-and-set found a different value in the register than the one expected
+    Mismatch,
 }
 
 /// Represents all possible directions at the game engine resolution
@@ -193,6 +452,12 @@ pub enum GridDirection {
     NorthWest = 7,
 }
 
+impl Default for GridDirection {
+    fn default() -> Self {
+        GridDirection::North
+    }
+}
+
 static ALL_DIRECTIONS: [GridDirection; 8] = [
     GridDirection::North,
     GridDirection::NorthEast,
@@ -213,6 +478,22 @@ pub struct MatchParameters {
     pub actors: Vec<String>,
     pub max_turns: u32,
     pub aps_per_turn: u32,
+    #[serde(default)]
+    pub ruleset: Ruleset,
+    #[serde(default)]
+    pub terrain: Vec<Obstacle>,
+    #[serde(default)]
+    pub turn_mode: TurnMode,
+    #[serde(default)]
+    pub turn_order: TurnOrder,
+    /// Drives everything random about the match, so that the same mechs playing a match
+    /// with the same seed will always produce the same events
+    #[serde(default)]
+    pub seed: u64,
+    /// Assigns mechs to teams, overriding the team each mech would otherwise join. A match
+    /// with team assignments is a team match, won by the last team standing
+    #[serde(default)]
+    pub teams: HashMap<String, String>,
 }
 
 impl MatchParameters {
@@ -223,6 +504,12 @@ impl MatchParameters {
         max_turns: u32,
         aps_per_turn: u32,
         actors: Vec<String>,
+        ruleset: Ruleset,
+        terrain: Vec<Obstacle>,
+        turn_mode: TurnMode,
+        turn_order: TurnOrder,
+        seed: u64,
+        teams: HashMap<String, String>,
     ) -> Self {
         MatchParameters {
             match_id,
@@ -231,21 +518,223 @@ impl MatchParameters {
             actors,
             max_turns,
             aps_per_turn,
+            ruleset,
+            terrain,
+            turn_mode,
+            turn_order,
+            seed,
+            teams,
+        }
+    }
+}
+
+/// Controls how the commands submitted by mechs during a turn are resolved
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum TurnMode {
+    /// Mechs take their turns one after the other, each seeing the results of the turns before it
+    Sequential,
+    /// Every mech plans its turn from the same snapshot of the match and all of the
+    /// commands are resolved at once
+    Simultaneous,
+}
+
+impl Default for TurnMode {
+    fn default() -> Self {
+        TurnMode::Sequential
+    }
+}
+
+/// The policy deciding the order in which mechs act during each turn of a sequential match
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum TurnOrder {
+    /// Mechs always act in the order they were listed for the match
+    Fixed,
+    /// The order is shuffled every turn using the match seed
+    Random,
+    /// The mech that acts first rotates one place through the listed order every turn, so
+    /// every mech gets to go first equally often
+    RoundRobin,
+    /// Mechs with the least remaining health act first, with ties kept in listed order
+    Initiative,
+}
+
+impl Default for TurnOrder {
+    fn default() -> Self {
+        TurnOrder::Fixed
+    }
+}
+
+/// The weapon, damage, and action point cost values in effect for a match. Any
+/// values not supplied when a ruleset is deserialized fall back to the standard rules
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Ruleset {
+    pub initial_health: u32,
+    pub wall_damage: u32,
+    pub pit_damage: u32,
+    pub primary_damage: u32,
+    pub primary_range: usize,
+    pub secondary_damage: u32,
+    pub secondary_splash_damage: u32,
+    pub secondary_range: usize,
+    pub move_cost: u32,
+    pub primary_cost: u32,
+    pub secondary_cost: u32,
+    pub radar_cost: u32,
+    pub rotate_cost: u32,
+    pub shield_cost: u32,
+    pub brace_cost: u32,
+    /// The percentage of incoming damage absorbed by a raised shield
+    pub shield_absorption: u32,
+    /// When set, mechs face a direction and can only fire and scan within an arc of
+    /// this many degrees centered on it, turning to face elsewhere with a rotate command
+    pub facing_arc: Option<u32>,
+    /// The rounds each mech starts with for its primary weapon. Unlimited when not set
+    pub primary_ammo: Option<u32>,
+    /// The rounds each mech starts with for its secondary weapon. Unlimited when not set
+    pub secondary_ammo: Option<u32>,
+    /// The number of turns a mech must wait after firing its primary weapon before firing it again
+    pub primary_cooldown: u32,
+    /// The number of turns a mech must wait after firing its secondary weapon before firing it again
+    pub secondary_cooldown: u32,
+    pub radar_pattern: RadarPattern,
+    pub ap_penalty: ApPenaltyPolicy,
+    pub friendly_fire: FriendlyFire,
+    pub pickups: PickupRules,
+    /// The milliseconds each mech is given to take its turn before it forfeits the turn.
+    /// Unlimited when 0
+    pub turn_timeout_millis: u64,
+    /// The number of timed out turns after which a mech is ejected from the match. Mechs
+    /// are never ejected when 0
+    pub max_timeouts: u32,
+    pub registers: RegisterFile,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Ruleset {
+            initial_health: state::INITIAL_HEALTH,
+            wall_damage: state::WALL_DAMAGE,
+            pit_damage: state::PIT_DAMAGE,
+            primary_damage: state::PRIMARY_DAMAGE,
+            primary_range: state::PRIMARY_RANGE,
+            secondary_damage: state::SECONDARY_DAMAGE,
+            secondary_splash_damage: state::SECONDARY_SPLASH_DAMAGE,
+            secondary_range: state::SECONDARY_RANGE,
+            move_cost: state::MOVE_COST,
+            primary_cost: state::PRIMARY_COST,
+            secondary_cost: state::SECONDARY_COST,
+            radar_cost: state::RADAR_COST,
+            rotate_cost: state::ROTATE_COST,
+            shield_cost: state::SHIELD_COST,
+            brace_cost: state::BRACE_COST,
+            shield_absorption: state::SHIELD_ABSORPTION,
+            facing_arc: None,
+            primary_ammo: None,
+            secondary_ammo: None,
+            primary_cooldown: 0,
+            secondary_cooldown: 0,
+            radar_pattern: RadarPattern::Star,
+            ap_penalty: ApPenaltyPolicy::Ignore,
+            friendly_fire: FriendlyFire::Full,
+            pickups: PickupRules::default(),
+            turn_timeout_millis: state::TURN_TIMEOUT_MILLIS,
+            max_timeouts: state::MAX_TIMEOUTS,
+            registers: RegisterFile::default(),
+        }
+    }
+}
+
+impl Ruleset {
+    /// The rounds each mech starts with for the given weapon, if its ammunition is limited
+    pub fn ammo(&self, weapon: WeaponType) -> Option<u32> {
+        match weapon {
+            WeaponType::Primary => self.primary_ammo,
+            WeaponType::Secondary => self.secondary_ammo,
+        }
+    }
+
+    pub fn cooldown(&self, weapon: WeaponType) -> u32 {
+        match weapon {
+            WeaponType::Primary => self.primary_cooldown,
+            WeaponType::Secondary => self.secondary_cooldown,
         }
     }
 }
 
+/// Why a mech's weapon refused to fire
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum WeaponUnavailable {
+    OutOfAmmo,
+    /// The mech tried to fire outside the arc around the direction it is facing
+    OutsideFiringArc,
+    /// The weapon can be fired again on the given turn
+    CoolingDown {
+        ready_on: u32,
+    },
+}
+
+/// How much weapon damage mechs deal to their own teammates. This only applies to team matches
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum FriendlyFire {
+    Full,
+    /// Teammates only take the given percentage of the damage
+    Reduced {
+        percent: u32,
+    },
+    Off,
+}
+
+/// How mechs are punished for issuing commands that exceed their remaining action points
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ApPenaltyPolicy {
+    /// The command is dropped and nothing else happens
+    Ignore,
+    /// The mech's hull takes the given amount of damage
+    HullDamage { damage: u32 },
+    /// The mech gets no action points on its next turn
+    ForfeitNextTurn,
+    /// The mech is removed from the match on its Nth violation
+    Disqualify { after: u32 },
+}
+
+/// A penalty handed to a mech for exceeding its action points or taking too long over its turns
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Penalty {
+    HullDamage(u32),
+    ActionPointsForfeited,
+    Disqualified,
+    /// The mech ran out of time on too many turns and was removed from the match
+    Ejected,
+}
+
+/// Why a command returned by an actor was refused before it reached the match
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum CommandViolation {
+    /// The command was issued on behalf of a mech other than the actor that returned it
+    NotOwner,
+    /// The command was issued for a turn other than the one being played
+    WrongTurn { current: u32 },
+    /// Actors may never issue this kind of command (e.g. spawning mechs)
+    Forbidden,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct TurnStatus {
     pub current: u32,
     pub taken: HashSet<String>,
+    /// The order in which mechs act during the current turn
+    #[serde(default)]
+    pub order: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DamageSource {
     Wall,
+    Pit,
     MechWeapon(String),
     MechCollision(String),
+    Penalty,
 }
 
 #[cfg(test)]
@@ -312,6 +801,29 @@ mod test {
         )
     }
 
+    #[test]
+    fn line_to() {
+        let origin = Point::new(2, 2);
+        assert_eq!(
+            origin.line_to(&Point::new(5, 5)),
+            vec![Point::new(3, 3), Point::new(4, 4), Point::new(5, 5)]
+        );
+        assert_eq!(
+            origin.line_to(&Point::new(2, 0)),
+            vec![Point::new(2, 1), Point::new(2, 0)]
+        );
+        assert_eq!(
+            origin.line_to(&Point::new(6, 4)),
+            vec![
+                Point::new(3, 3),
+                Point::new(4, 3),
+                Point::new(5, 4),
+                Point::new(6, 4)
+            ]
+        );
+        assert!(origin.line_to(&origin).is_empty());
+    }
+
     #[test]
     fn compute_bearing() {
         // this should be a 45 degree bearing, or NorthEast
@@ -360,4 +872,49 @@ mod test {
         assert_eq!(mech_position.distance_to(&Point::new(3, 3)), 0);
         assert_eq!(mech_position.distance_to(&Point::new(123, 456)), 468);
     }
+
+    #[test]
+    fn path_avoids_terrain() {
+        let board = GameBoard {
+            width: 10,
+            height: 10,
+            terrain: vec![
+                Obstacle::new(Point::new(3, 2), TerrainType::Wall),
+                Obstacle::new(Point::new(3, 3), TerrainType::Pit),
+                Obstacle::new(Point::new(3, 4), TerrainType::Wall),
+            ],
+        };
+        let origin = Point::new(2, 3);
+        let path = board.path(&origin, &Point::new(4, 3)).unwrap();
+        assert_eq!(path.len(), 4);
+        let end = path.iter().fold(origin.clone(), |p, dir| {
+            let next = p.offset(dir, 1);
+            assert!(board.obstacle_at(&next).is_none());
+            next
+        });
+        assert_eq!(end, Point::new(4, 3));
+
+        assert_eq!(board.path(&origin, &origin), Some(vec![]));
+        assert_eq!(board.path(&origin, &Point::new(3, 3)), None);
+        assert_eq!(board.path(&origin, &Point::new(11, 3)), None);
+    }
+
+    #[test]
+    fn register_file_limits() {
+        let huge = RegisterFile {
+            numeric: u32::MAX,
+            text: 2,
+            memory_quota: 0,
+        };
+        assert!(huge.validate().is_err());
+        assert!(RegisterFile::default().validate().is_ok());
+        // an oversized numeric bank doesn't spill into the text registers
+        assert_eq!(huge.kind(text_register(0)), Some(RegisterKind::Text));
+        assert_eq!(huge.kind(text_register(2)), None);
+        assert_eq!(huge.kind(u32::MAX), None);
+        assert_eq!(
+            huge.kind(numeric_register(MAX_GENERAL_REGISTERS - 1)),
+            Some(RegisterKind::Number)
+        );
+    }
 }
@@ -0,0 +1,109 @@
+use crate::{
+    events::GameEvent,
+    state::{MatchState, MechState},
+    Point, SeededRng,
+};
+
+/// The benefit a mech receives for collecting a pickup
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PickupKind {
+    /// Restores hull integrity, up to the match's initial health
+    RepairKit { health: u32 },
+    /// Adds rounds to each of the mech's weapons that have limited ammunition
+    Ammo { rounds: u32 },
+    /// Grants extra action points for the remainder of the current turn
+    ActionPointBoost { points: u32 },
+}
+
+/// An item lying on the game board, waiting for a mech to move onto it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Pickup {
+    pub position: Point,
+    pub kind: PickupKind,
+}
+
+impl Pickup {
+    pub fn new(position: Point, kind: PickupKind) -> Pickup {
+        Pickup { position, kind }
+    }
+
+    /// Returns the given mech with the benefits of this pickup applied
+    pub(crate) fn apply(&self, mech: MechState, max_health: u32) -> MechState {
+        match self.kind {
+            PickupKind::RepairKit { health } => MechState {
+                health: mech
+                    .health
+                    .saturating_add(health)
+                    .min(max_health.max(mech.health)),
+                ..mech
+            },
+            PickupKind::Ammo { rounds } => MechState {
+                ammo: mech
+                    .ammo
+                    .iter()
+                    .map(|(weapon, left)| (*weapon, left.saturating_add(rounds)))
+                    .collect(),
+                ..mech
+            },
+            PickupKind::ActionPointBoost { points } => MechState {
+                remaining_aps: mech.remaining_aps.saturating_add(points),
+                ..mech
+            },
+        }
+    }
+}
+
+/// Controls how often pickups appear on the board and what they contain
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PickupRules {
+    /// The number of turns between pickup spawns. Pickups are disabled when this is 0
+    pub interval: u32,
+    /// The most pickups that may be lying on the board at once
+    pub limit: u32,
+    /// The kinds of pickup to choose from when spawning
+    pub kinds: Vec<PickupKind>,
+}
+
+impl Default for PickupRules {
+    fn default() -> Self {
+        PickupRules {
+            interval: 0,
+            limit: 3,
+            kinds: vec![
+                PickupKind::RepairKit { health: 200 },
+                PickupKind::Ammo { rounds: 2 },
+                PickupKind::ActionPointBoost { points: 2 },
+            ],
+        }
+    }
+}
+
+/// Picks a kind and a spot for a new pickup if one is due on the given turn. The spot is
+/// the nearest unoccupied point to a random point on the board. Nothing spawns on a board
+/// with no room on it
+pub(crate) fn spawn_pickup(state: &MatchState, turn: u32) -> Vec<GameEvent> {
+    let rules = &state.parameters.ruleset.pickups;
+    if rules.interval == 0
+        || turn == 0
+        || turn % rules.interval != 0
+        || rules.kinds.is_empty()
+        || state.pickups.len() >= rules.limit as usize
+        || state.parameters.width == 0
+        || state.parameters.height == 0
+    {
+        return Vec::new();
+    }
+    let mut rng = SeededRng::for_stream(state.parameters.seed, &format!("pickups-{}", turn));
+    let kind = rules.kinds[rng.below(rules.kinds.len() as u64) as usize].clone();
+    let candidate = Point::new(
+        rng.between(1, state.parameters.width - 1) as _,
+        rng.between(1, state.parameters.height - 1) as _,
+    );
+    MatchState::nearest_unoccupied(state, &Some(candidate))
+        .map(|position| GameEvent::PickupSpawned {
+            pickup: Pickup::new(position, kind),
+        })
+        .into_iter()
+        .collect()
+}
@@ -1,6 +1,6 @@
 use crate::{
     state::{MatchState, MechState},
-    GridDirection, Point,
+    GridDirection, Obstacle, Pickup, PickupKind, Point, TerrainType,
 };
 
 const RADAR_GRID: [(GridDirection, i32); 8] = [
@@ -14,6 +14,39 @@ const RADAR_GRID: [(GridDirection, i32); 8] = [
     (GridDirection::SouthEast, 3),
 ];
 
+/// The shape of the area swept by a radar scan. Regardless of the pattern, the first
+/// mech or piece of solid terrain along the line of sight hides anything behind it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum RadarPattern {
+    /// Eight rays reaching 4 units along the cardinal directions and 3 along the diagonals
+    Star,
+    /// Every point within the given radius of the scanning mech
+    Circle { radius: u32 },
+    /// Every point within the given radius that falls inside an arc (in degrees) centered
+    /// on the direction of the scan
+    Cone { radius: u32, arc: u32 },
+}
+
+impl Default for RadarPattern {
+    fn default() -> Self {
+        RadarPattern::Star
+    }
+}
+
+/// Indicates what kind of object produced a radar ping
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PingKind {
+    Mech,
+    Obstacle(TerrainType),
+    Pickup(PickupKind),
+}
+
+impl Default for PingKind {
+    fn default() -> Self {
+        PingKind::Mech
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct RadarPing {
     pub name: String,
@@ -21,12 +54,94 @@ pub struct RadarPing {
     pub foe: bool,
     pub location: Point,
     pub distance: usize,
+    #[serde(default)]
+    pub kind: PingKind,
+}
+
+/// Returns every on-board point covered by the match's radar pattern along with its
+/// distance from the origin. Cone scans without a direction face north
+fn sweep(
+    state: &MatchState,
+    origin: &Point,
+    direction: &Option<GridDirection>,
+) -> Vec<(Point, usize)> {
+    match &state.parameters.ruleset.radar_pattern {
+        RadarPattern::Star => RADAR_GRID
+            .iter()
+            .flat_map(|(dir, length)| {
+                (1..=*length).map(move |d| (origin.offset(dir, d), d as usize))
+            })
+            .filter(|(p, _d)| p.is_on_board(&state.game_board))
+            .collect(),
+        RadarPattern::Circle { radius } => sweep_area(state, origin, *radius, |_p| true),
+        RadarPattern::Cone { radius, arc } => {
+            let facing = direction.unwrap_or(GridDirection::North);
+            sweep_area(state, origin, *radius, |p| {
+                within_arc(origin, p, &facing, *arc)
+            })
+        }
+    }
+}
+
+fn sweep_area<F>(state: &MatchState, origin: &Point, radius: u32, filter: F) -> Vec<(Point, usize)>
+where
+    F: Fn(&Point) -> bool,
+{
+    let r = radius as i32;
+    let mut points = Vec::new();
+    for x in origin.x - r..=origin.x + r {
+        for y in origin.y - r..=origin.y + r {
+            let p = Point::new(x, y);
+            if p == *origin || !p.is_on_board(&state.game_board) {
+                continue;
+            }
+            let distance = origin.distance_to(&p);
+            if distance <= radius && filter(&p) {
+                points.push((p, distance as usize));
+            }
+        }
+    }
+    points.sort_by_key(|(p, d)| (*d, p.x, p.y));
+    points
+}
+
+pub(crate) fn within_arc(origin: &Point, target: &Point, facing: &GridDirection, arc: u32) -> bool {
+    let dx = (target.x - origin.x) as f64;
+    let dy = (target.y - origin.y) as f64;
+    let angle = (90.0 - dy.atan2(dx).to_degrees() + 360.0) % 360.0;
+    let facing_angle = *facing as u32 as f64 * 45.0;
+    let diff = (angle - facing_angle).abs();
+    diff.min(360.0 - diff) <= arc as f64 / 2.0
 }
 
-pub(crate) fn collect_targets(state: &MatchState, origin: &Point) -> Vec<(MechState, usize)> {
-    RADAR_GRID
+/// A point is visible when no mech or solid terrain lies on the straight line between
+/// it and the origin
+fn is_visible(state: &MatchState, origin: &Point, target: &Point) -> bool {
+    let line = origin.line_to(target);
+    line[..line.len() - 1]
         .iter()
-        .flat_map(|(dir, length)| origin.gather_points(&state.game_board, dir, *length as usize))
+        .all(|p| MatchState::mech_at(state, p).is_none() && !state.game_board.is_blocked(p))
+}
+
+fn visible_points(
+    state: &MatchState,
+    origin: &Point,
+    direction: &Option<GridDirection>,
+) -> Vec<(Point, usize)> {
+    sweep(state, origin, direction)
+        .into_iter()
+        .filter(|(p, _d)| is_visible(state, origin, p))
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) fn collect_targets(
+    state: &MatchState,
+    origin: &Point,
+    direction: &Option<GridDirection>,
+) -> Vec<(MechState, usize)> {
+    visible_points(state, origin, direction)
+        .into_iter()
         .filter_map(|(p, d)| MatchState::mech_at(state, &p).map(|m| (m, d)))
         .collect::<Vec<_>>()
 }
@@ -35,10 +150,19 @@ pub(crate) fn radar_ping(
     state: &MatchState,
     origin: &Point,
     scanning_team: &str,
+    direction: &Option<GridDirection>,
 ) -> Vec<RadarPing> {
-    collect_targets(state, origin)
+    visible_points(state, origin, direction)
         .into_iter()
-        .map(|(t, d)| mech_to_ping(t, scanning_team, d))
+        .filter_map(|(p, d)| {
+            if let Some(mech) = MatchState::mech_at(state, &p) {
+                Some(mech_to_ping(mech, scanning_team, d))
+            } else if let Some(o) = state.game_board.obstacle_at(&p) {
+                Some(obstacle_to_ping(o.clone(), d))
+            } else {
+                MatchState::pickup_at(state, &p).map(|pickup| pickup_to_ping(pickup, d))
+            }
+        })
         .collect()
 }
 
@@ -49,5 +173,28 @@ pub(crate) fn mech_to_ping(mech: MechState, scanning_team: &str, distance: usize
         location: mech.position,
         distance,
         foe: mech.team.to_lowercase() != scanning_team.to_lowercase(),
+        kind: PingKind::Mech,
+    }
+}
+
+pub(crate) fn obstacle_to_ping(obstacle: Obstacle, distance: usize) -> RadarPing {
+    RadarPing {
+        name: format!("obstacle-{}-{}", obstacle.position.x, obstacle.position.y),
+        avatar: "none".to_string(),
+        location: obstacle.position,
+        distance,
+        foe: false,
+        kind: PingKind::Obstacle(obstacle.terrain),
+    }
+}
+
+pub(crate) fn pickup_to_ping(pickup: Pickup, distance: usize) -> RadarPing {
+    RadarPing {
+        name: format!("pickup-{}-{}", pickup.position.x, pickup.position.y),
+        avatar: "none".to_string(),
+        location: pickup.position,
+        distance,
+        foe: false,
+        kind: PingKind::Pickup(pickup.kind),
     }
 }
@@ -0,0 +1,86 @@
+/// A small SplitMix64 pseudo-random number generator. Everything random about a match is
+/// drawn from one of these so that the same match seed always produces the same match
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> SeededRng {
+        SeededRng { state: seed }
+    }
+
+    /// Creates a generator for a named stream of numbers (e.g. spawn placement or a
+    /// single mech's turn) that is independent of the other streams drawn from the same seed
+    pub fn for_stream(seed: u64, stream: &str) -> SeededRng {
+        // FNV-1a, so stream names hash the same on every platform and release
+        let hash = stream.bytes().fold(0xCBF2_9CE4_8422_2325_u64, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x0100_0000_01B3)
+        });
+        SeededRng::new(seed ^ hash)
+    }
+
+    /// Derives the seed for a named stream that is handed to someone who mustn't learn the
+    /// match seed, such as a mech. Unlike the state of `for_stream`, the result can't simply
+    /// be worked back to the match seed
+    pub fn derive_seed(seed: u64, stream: &str) -> u64 {
+        let mut rng = SeededRng::for_stream(seed, stream);
+        rng.next_u64() ^ rng.next_u64().rotate_left(17)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in the range `0..bound`
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    /// Returns a number between the min and max values (inclusive)
+    pub fn between(&mut self, min: u32, max: u32) -> u32 {
+        if max <= min {
+            min
+        } else {
+            min + self.below((max - min) as u64 + 1) as u32
+        }
+    }
+
+    /// Shuffles the items in place (Fisher-Yates)
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SeededRng;
+
+    #[test]
+    fn streams_are_reproducible() {
+        let draw = |stream: &str| {
+            let mut rng = SeededRng::for_stream(42, stream);
+            (0..20).map(|_| rng.between(1, 6)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(draw("al-0"), draw("al-0"));
+        assert_ne!(draw("al-0"), draw("al-1"));
+        assert!(draw("bob-0").iter().all(|n| *n >= 1 && *n <= 6));
+    }
+
+    #[test]
+    fn derived_seeds_hide_the_match_seed() {
+        let derived = SeededRng::derive_seed(42, "al-0");
+        assert_eq!(derived, SeededRng::derive_seed(42, "al-0"));
+        assert_ne!(derived, SeededRng::derive_seed(42, "al-1"));
+        assert_ne!(derived, SeededRng::derive_seed(43, "al-0"));
+        assert_ne!(derived, SeededRng::for_stream(42, "al-0").state);
+    }
+}
@@ -1,19 +1,39 @@
 use crate::{
-    commands::MechCommand,
+    commands::{CommandRejection, MechCommand},
     events::{EndCause, GameEvent},
-    DamageSource, GameBoard, GridDirection, MatchParameters, Point, RadarPing, RegisterOperation,
-    RegisterValue, TurnStatus, EAX, EBX, ECX,
+    pickups, radar,
+    rng::SeededRng,
+    ApPenaltyPolicy, CommandViolation, DamageSource, FriendlyFire, GameBoard, GridDirection,
+    MatchParameters, Obstacle, Penalty, Pickup, Point, RadarPing, RegisterFault, RegisterKind,
+    RegisterOperation, RegisterValue, TerrainType, TurnOrder, TurnStatus, WeaponType,
+    WeaponUnavailable,
 };
 use eventsourcing::Result;
 use eventsourcing::{Aggregate, AggregateState};
-use std::collections::HashMap;
-
-const WALL_DAMAGE: u32 = 50; // Lose HP for bouncing off obstacles
-const PRIMARY_DAMAGE: u32 = 100;
-const SECONDARY_DAMAGE: u32 = 140;
-const SECONDARY_SPLASH_DAMAGE: u32 = 90;
-
-const INITIAL_HEALTH: u32 = 1000;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+// Default values for the standard ruleset
+pub(crate) const WALL_DAMAGE: u32 = 50; // Lose HP for bouncing off obstacles
+pub(crate) const PIT_DAMAGE: u32 = 200;
+pub(crate) const PRIMARY_DAMAGE: u32 = 100;
+pub(crate) const SECONDARY_DAMAGE: u32 = 140;
+pub(crate) const SECONDARY_SPLASH_DAMAGE: u32 = 90;
+
+pub(crate) const MOVE_COST: u32 = 1;
+pub(crate) const PRIMARY_COST: u32 = 2;
+pub(crate) const SECONDARY_COST: u32 = 4;
+pub(crate) const RADAR_COST: u32 = 1;
+pub(crate) const ROTATE_COST: u32 = 1;
+pub(crate) const SHIELD_COST: u32 = 2;
+pub(crate) const BRACE_COST: u32 = 1;
+pub(crate) const SHIELD_ABSORPTION: u32 = 50;
+
+// Turns aren't timed and mechs aren't ejected unless a match's ruleset asks for it
+pub(crate) const TURN_TIMEOUT_MILLIS: u64 = 0;
+pub(crate) const MAX_TIMEOUTS: u32 = 0;
+
+pub(crate) const INITIAL_HEALTH: u32 = 1000;
 pub const PRIMARY_RANGE: usize = 3;
 pub const SECONDARY_RANGE: usize = 6;
 pub const APS_PER_TURN: u32 = 4;
@@ -27,6 +47,9 @@ pub struct MatchState {
     pub turn_status: TurnStatus,
     pub completed: Option<EndCause>,
     pub radar_pings: HashMap<String, Vec<RadarPing>>,
+    /// Pickups lying on the board waiting to be collected
+    #[serde(default)]
+    pub pickups: Vec<Pickup>,
 }
 
 impl MatchState {
@@ -38,10 +61,104 @@ impl MatchState {
             game_board: GameBoard {
                 height: params.height,
                 width: params.width,
+                terrain: params.terrain.clone(),
             },
             completed: None,
             turn_status: Default::default(),
             radar_pings: HashMap::new(),
+            pickups: Vec::new(),
+        }
+    }
+
+    /// Produces the match as the given mech is allowed to see it: the public match
+    /// parameters and terrain, plus its own mech, team and radar results. Everything else
+    /// about the other mechs and the pickups on the board has to be discovered by radar.
+    /// The seed is withheld, since it would let a mech predict spawn points, pickups and
+    /// the other mechs' random numbers
+    pub fn view_for(state: &MatchState, mech: &str) -> MatchState {
+        let own = |id: &String| id == mech;
+        MatchState {
+            parameters: MatchParameters {
+                seed: 0,
+                teams: state
+                    .parameters
+                    .teams
+                    .iter()
+                    .filter(|(id, _team)| own(id))
+                    .map(|(id, team)| (id.to_string(), team.to_string()))
+                    .collect(),
+                ..state.parameters.clone()
+            },
+            mechs: state
+                .mechs
+                .iter()
+                .filter(|(id, _m)| own(id))
+                .map(|(id, m)| (id.to_string(), m.clone()))
+                .collect(),
+            generation: state.generation,
+            game_board: state.game_board.clone(),
+            turn_status: TurnStatus {
+                current: state.turn_status.current,
+                taken: state
+                    .turn_status
+                    .taken
+                    .iter()
+                    .filter(|id| own(id))
+                    .cloned()
+                    .collect(),
+                order: Vec::new(),
+            },
+            completed: state.completed.clone(),
+            radar_pings: state
+                .radar_pings
+                .iter()
+                .filter(|(id, _p)| own(id))
+                .map(|(id, p)| (id.to_string(), p.clone()))
+                .collect(),
+            pickups: Vec::new(),
+        }
+    }
+
+    /// Produces the match as a spectator is allowed to see it: the public match parameters
+    /// and terrain, and where each mech stands and how much damage it has taken, which
+    /// spectators follow through the match's events anyway. Each mech's registers,
+    /// ammunition, cooldowns and penalties, the radar results, the pickups, the turn order
+    /// and the seed are all withheld
+    pub fn spectator_view(state: &MatchState) -> MatchState {
+        MatchState {
+            parameters: MatchParameters {
+                seed: 0,
+                ..state.parameters.clone()
+            },
+            mechs: state
+                .mechs
+                .iter()
+                .map(|(id, m)| {
+                    let mech = MechState {
+                        remaining_aps: 0,
+                        registers: HashMap::new(),
+                        ap_violations: 0,
+                        forfeit_next_turn: false,
+                        penalties: Vec::new(),
+                        ammo: HashMap::new(),
+                        cooldowns: HashMap::new(),
+                        rejections: Vec::new(),
+                        timeouts: 0,
+                        ..m.clone()
+                    };
+                    (id.to_string(), mech)
+                })
+                .collect(),
+            generation: state.generation,
+            game_board: state.game_board.clone(),
+            turn_status: TurnStatus {
+                current: state.turn_status.current,
+                taken: state.turn_status.taken.clone(),
+                order: Vec::new(),
+            },
+            completed: state.completed.clone(),
+            radar_pings: HashMap::new(),
+            pickups: Vec::new(),
         }
     }
 
@@ -57,9 +174,50 @@ impl MatchState {
         }
     }
 
+    /// Checks that the registers an operation touches exist in the match's register file
+    /// and that it won't overflow the scratch memory, returning the kind of value the
+    /// register holds
+    fn validate_register(
+        state: &MatchState,
+        reg: u32,
+        op: &RegisterOperation,
+    ) -> Result<RegisterKind> {
+        let registers = &state.parameters.ruleset.registers;
+        let fail = |msg: String| {
+            Err(eventsourcing::Error {
+                kind: eventsourcing::Kind::CommandFailure(msg),
+            })
+        };
+        let other = match op {
+            RegisterOperation::Copy { from: other } | RegisterOperation::Swap { with: other } => {
+                Some(*other)
+            }
+            _ => None,
+        };
+        let bytes = match op {
+            RegisterOperation::Set(RegisterValue::Bytes(b))
+            | RegisterOperation::CompareAndSet {
+                new: RegisterValue::Bytes(b),
+                ..
+            } => b.len(),
+            _ => 0,
+        };
+        match (registers.kind(reg), other) {
+            (None, _) => fail(format!("Register {} does not exist", reg)),
+            (_, Some(other)) if registers.kind(other).is_none() => {
+                fail(format!("Register {} does not exist", other))
+            }
+            _ if bytes > registers.memory_quota => fail(format!(
+                "{} bytes exceeds the memory quota of {}",
+                bytes, registers.memory_quota
+            )),
+            (Some(kind), _) => Ok(kind),
+        }
+    }
+
     fn validate_can_take_action(state: &MatchState, mech: &str, cmd: &MechCommand) -> Result<()> {
         MatchState::validate_has_mech(state, mech)?;
-        if state.mechs[mech].remaining_aps >= cmd.action_points() {
+        if state.mechs[mech].remaining_aps >= cmd.action_points(&state.parameters.ruleset) {
             Ok(())
         } else {
             Err(eventsourcing::Error {
@@ -70,6 +228,58 @@ impl MatchState {
         }
     }
 
+    /// The direction the mech faces and the width of its arc of fire, if the match's rules
+    /// give mechs a facing
+    pub(crate) fn facing_arc(state: &MatchState, mech: &str) -> Option<(GridDirection, u32)> {
+        match (state.parameters.ruleset.facing_arc, state.mechs.get(mech)) {
+            (Some(arc), Some(m)) => Some((m.facing, arc)),
+            _ => None,
+        }
+    }
+
+    /// Determines whether the weapon fired by the given command is out of ammunition
+    /// or still cooling down
+    fn weapon_unavailable(
+        state: &MatchState,
+        mech: &str,
+        cmd: &MechCommand,
+    ) -> Option<WeaponUnavailable> {
+        let weapon = cmd.weapon()?;
+        let in_arc = match (MatchState::facing_arc(state, mech), cmd.aim()) {
+            (Some((facing, arc)), Some(aim)) => {
+                let origin = &state.mechs[mech].position;
+                radar::within_arc(origin, &origin.offset(&aim, 1), &facing, arc)
+            }
+            _ => true,
+        };
+        let mech = state.mechs.get(mech)?;
+        match (mech.ammo.get(&weapon), mech.cooldowns.get(&weapon)) {
+            _ if !in_arc => Some(WeaponUnavailable::OutsideFiringArc),
+            (Some(0), _) => Some(WeaponUnavailable::OutOfAmmo),
+            (_, Some(ready_on)) if *ready_on > state.turn_status.current => {
+                Some(WeaponUnavailable::CoolingDown {
+                    ready_on: *ready_on,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn discharge_weapon(state: &MatchState, mech: &str, weapon: WeaponType) -> MatchState {
+        let cooldown = state.parameters.ruleset.cooldown(weapon);
+        let turn = state.turn_status.current;
+        MatchState::modify_mech(state, mech, |m| {
+            let mut m = m;
+            if let Some(left) = m.ammo.get_mut(&weapon) {
+                *left = left.saturating_sub(1);
+            }
+            if cooldown > 0 {
+                m.cooldowns.insert(weapon, turn + cooldown + 1);
+            }
+            m
+        })
+    }
+
     fn update_radar(state: &MatchState, actor: &str, pings: &[RadarPing]) -> MatchState {
         let mut state = state.clone();
         state.radar_pings.insert(actor.to_string(), pings.to_vec());
@@ -82,14 +292,27 @@ impl MatchState {
         state
     }
 
+    fn is_team_match(state: &MatchState) -> bool {
+        !state.parameters.teams.is_empty()
+    }
+
+    fn teammates(state: &MatchState, a: &str, b: &str) -> bool {
+        match (state.mechs.get(a), state.mechs.get(b)) {
+            (Some(a), Some(b)) => MatchState::is_team_match(state) && a.team == b.team,
+            _ => false,
+        }
+    }
+
     fn remaining_alive(state: &MatchState) -> Vec<String> {
         let mechs = state.mechs.clone();
 
-        mechs
+        let mut alive: Vec<_> = mechs
             .into_iter()
             .filter(|(_key, m)| m.alive)
             .map(|(key, _m)| key)
-            .collect()
+            .collect();
+        alive.sort(); // keep events identical from one run to the next
+        alive
     }
 
     fn modify_mech<F>(state: &MatchState, mech: &str, fun: F) -> MatchState
@@ -121,29 +344,54 @@ impl MatchState {
             .cloned()
     }
 
+    pub(crate) fn pickup_at(state: &MatchState, position: &Point) -> Option<Pickup> {
+        state
+            .pickups
+            .iter()
+            .find(|p| p.position == *position)
+            .cloned()
+    }
+
+    /// Finds the closest point to the given one that isn't taken up by a mech, terrain or a pickup
     pub(crate) fn nearest_unoccupied(
         state: &MatchState,
         position: &Option<Point>,
     ) -> Option<Point> {
-        if let Some(position) = position {
-            if Self::mech_at(state, position).is_some() {
-                vec![
-                    position.relative_point(&state.game_board, &GridDirection::North, 1),
-                    position.relative_point(&state.game_board, &GridDirection::NorthEast, 1),
-                    position.relative_point(&state.game_board, &GridDirection::East, 1),
-                    position.relative_point(&state.game_board, &GridDirection::SouthEast, 1),
-                    position.relative_point(&state.game_board, &GridDirection::South, 1),
-                    position.relative_point(&state.game_board, &GridDirection::SouthWest, 1),
-                    position.relative_point(&state.game_board, &GridDirection::West, 1),
-                    position.relative_point(&state.game_board, &GridDirection::NorthWest, 1),
-                ]
-                .iter()
-                .find_map(|p| Self::nearest_unoccupied(state, p))
-            } else {
-                Some(position.clone())
-            }
+        Self::search_unoccupied(state, position, &mut HashSet::new())
+    }
+
+    /// Searches outward from the position, north first, skipping points already searched
+    /// so that the search ends (finding nothing) when the board is full
+    fn search_unoccupied(
+        state: &MatchState,
+        position: &Option<Point>,
+        searched: &mut HashSet<(i32, i32)>,
+    ) -> Option<Point> {
+        let position = position.as_ref()?;
+        if !searched.insert((position.x, position.y)) {
+            return None;
+        }
+        if Self::mech_at(state, position).is_some()
+            || state.game_board.obstacle_at(position).is_some()
+            || Self::pickup_at(state, position).is_some()
+        {
+            [
+                GridDirection::North,
+                GridDirection::NorthEast,
+                GridDirection::East,
+                GridDirection::SouthEast,
+                GridDirection::South,
+                GridDirection::SouthWest,
+                GridDirection::West,
+                GridDirection::NorthWest,
+            ]
+            .iter()
+            .find_map(|dir| {
+                let next = position.relative_point(&state.game_board, dir, 1);
+                Self::search_unoccupied(state, &next, searched)
+            })
         } else {
-            None
+            Some(position.clone())
         }
     }
 
@@ -164,6 +412,11 @@ impl MatchState {
                 team: team.to_string(),
                 avatar: avatar.to_string(),
                 name: name.to_string(),
+                health: state.parameters.ruleset.initial_health,
+                ammo: [WeaponType::Primary, WeaponType::Secondary]
+                    .iter()
+                    .filter_map(|w| state.parameters.ruleset.ammo(*w).map(|rounds| (*w, rounds)))
+                    .collect(),
                 ..Default::default()
             },
         );
@@ -173,13 +426,82 @@ impl MatchState {
         }
     }
 
+    fn damage_terrain(state: &MatchState, position: &Point, damage: u32) -> MatchState {
+        let mut state = state.clone();
+        for obstacle in state.game_board.terrain.iter_mut() {
+            if obstacle.position == *position {
+                if let TerrainType::Cover { health } = obstacle.terrain {
+                    obstacle.terrain = TerrainType::Cover {
+                        health: health - damage.min(health),
+                    };
+                }
+            }
+        }
+        state
+    }
+
+    fn add_pickup(state: &MatchState, pickup: &Pickup) -> MatchState {
+        let mut state = state.clone();
+        state.pickups.push(pickup.clone());
+        state
+    }
+
+    fn collect_pickup(state: &MatchState, mech: &str, pickup: &Pickup) -> MatchState {
+        let max_health = state.parameters.ruleset.initial_health;
+        let mut state = MatchState::modify_mech(state, mech, |m| pickup.apply(m, max_health));
+        state.pickups.retain(|p| p.position != pickup.position);
+        state
+    }
+
+    fn remove_terrain(state: &MatchState, position: &Point) -> MatchState {
+        let mut state = state.clone();
+        state.game_board.terrain.retain(|o| o.position != *position);
+        state
+    }
+
     fn advance_match_turn(state: &MatchState, turn: u32) -> MatchState {
         let mut state = MatchState::reset_mech_action_points(state);
         state.turn_status.taken.clear();
         state.turn_status.current = turn;
+        for mech in state.mechs.values_mut() {
+            // Mechs only need to hear about penalties from the turn that just ended
+            mech.penalties.retain(|(t, _p)| t + 1 >= turn);
+            mech.rejections.retain(|r| r.turn + 1 >= turn);
+        }
+        state
+    }
+
+    fn set_turn_order(state: &MatchState, order: &[String]) -> MatchState {
+        let mut state = state.clone();
+        state.turn_status.order = order.to_vec();
         state
     }
 
+    fn turn_order(state: &MatchState, turn: u32) -> Vec<String> {
+        let mut order = state.parameters.actors.clone();
+        match &state.parameters.turn_order {
+            TurnOrder::Fixed => {}
+            TurnOrder::Random => {
+                SeededRng::for_stream(state.parameters.seed, &format!("turn-order-{}", turn))
+                    .shuffle(&mut order)
+            }
+            TurnOrder::RoundRobin => {
+                if !order.is_empty() {
+                    let first = turn as usize % order.len();
+                    order.rotate_left(first);
+                }
+            }
+            TurnOrder::Initiative => order.sort_by_key(|mech| {
+                // Destroyed mechs go last
+                state
+                    .mechs
+                    .get(mech)
+                    .map_or((true, 0), |m| (!m.alive, m.health))
+            }),
+        }
+        order
+    }
+
     fn advance_mech_turn(state: &MatchState, mech: &str) -> MatchState {
         let mut state = state.clone();
         state.turn_status.taken.insert(mech.to_string());
@@ -213,7 +535,12 @@ impl MatchState {
                 (
                     mech.clone(),
                     MechState {
-                        remaining_aps: state.parameters.aps_per_turn,
+                        remaining_aps: if mech_state.forfeit_next_turn {
+                            0
+                        } else {
+                            state.parameters.aps_per_turn
+                        },
+                        forfeit_next_turn: false,
                         ..mech_state.clone()
                     },
                 )
@@ -254,6 +581,36 @@ pub struct MechState {
     pub name: String,
     pub remaining_aps: u32,
     pub registers: HashMap<u32, RegisterValue>,
+    /// The number of commands this mech has issued that exceeded its remaining action points
+    #[serde(default)]
+    pub ap_violations: u32,
+    #[serde(default)]
+    pub forfeit_next_turn: bool,
+    /// Penalties handed to this mech during the current and previous turns, along with
+    /// the turn in which each was handed out
+    #[serde(default)]
+    pub penalties: Vec<(u32, Penalty)>,
+    /// The rounds left for each of the mech's weapons that have limited ammunition
+    #[serde(default)]
+    pub ammo: HashMap<WeaponType, u32>,
+    /// The turn on which each weapon that is cooling down can next be fired
+    #[serde(default)]
+    pub cooldowns: HashMap<WeaponType, u32>,
+    /// The direction the mech is facing, which only matters when the match's rules give mechs a facing
+    #[serde(default)]
+    pub facing: GridDirection,
+    /// The turn in which the mech raised its shield, if the shield is up
+    #[serde(default)]
+    pub shield_raised: Option<u32>,
+    /// The turn in which the mech braced itself, if it is still braced
+    #[serde(default)]
+    pub braced: Option<u32>,
+    /// Commands the arena refused to carry out during the current and previous turns
+    #[serde(default)]
+    pub rejections: Vec<CommandRejection>,
+    /// The number of turns on which this mech failed to respond in time
+    #[serde(default)]
+    pub timeouts: u32,
 }
 
 impl Default for MechState {
@@ -269,6 +626,16 @@ impl Default for MechState {
             "Anonymous".to_string(),
             4,
             HashMap::new(),
+            0,
+            false,
+            Vec::new(),
+            HashMap::new(),
+            HashMap::new(),
+            GridDirection::North,
+            None,
+            None,
+            Vec::new(),
+            0,
         )
     }
 }
@@ -285,6 +652,16 @@ impl MechState {
         name: String,
         remaining_aps: u32,
         registers: HashMap<u32, RegisterValue>,
+        ap_violations: u32,
+        forfeit_next_turn: bool,
+        penalties: Vec<(u32, Penalty)>,
+        ammo: HashMap<WeaponType, u32>,
+        cooldowns: HashMap<WeaponType, u32>,
+        facing: GridDirection,
+        shield_raised: Option<u32>,
+        braced: Option<u32>,
+        rejections: Vec<CommandRejection>,
+        timeouts: u32,
     ) -> Self {
         MechState {
             health,
@@ -297,6 +674,16 @@ impl MechState {
             name,
             remaining_aps,
             registers,
+            ap_violations,
+            forfeit_next_turn,
+            penalties,
+            ammo,
+            cooldowns,
+            facing,
+            shield_raised,
+            braced,
+            rejections,
+            timeouts,
         }
     }
 }
@@ -318,6 +705,34 @@ impl Aggregate for Match {
             } => Ok(MatchState::insert_mech(
                 state, mech, position, team, avatar, name,
             )),
+            GameEvent::ShieldRaised { mech } => {
+                let turn = state.turn_status.current;
+                Ok(MatchState::modify_mech(state, mech, |m| MechState {
+                    shield_raised: Some(turn),
+                    ..m
+                }))
+            }
+            GameEvent::Braced { mech } => {
+                let turn = state.turn_status.current;
+                Ok(MatchState::modify_mech(state, mech, |m| MechState {
+                    braced: Some(turn),
+                    ..m
+                }))
+            }
+            GameEvent::DefensesLowered { mech } => {
+                Ok(MatchState::modify_mech(state, mech, |m| MechState {
+                    shield_raised: None,
+                    braced: None,
+                    ..m
+                }))
+            }
+            GameEvent::DamageBlocked { .. } => Ok(state.clone()),
+            GameEvent::FacingChanged { mech, facing } => {
+                Ok(MatchState::modify_mech(state, mech, |m| MechState {
+                    facing: *facing,
+                    ..m
+                }))
+            }
             GameEvent::RadarScanCompleted { actor, results } => {
                 Ok(MatchState::update_radar(state, actor, results))
             }
@@ -335,10 +750,50 @@ impl Aggregate for Match {
                 mech,
                 points_consumed,
             )),
-            GameEvent::ActionPointsExceeded { .. } => {
-                //TODO: Handle the penalty for exceeding action points appropriately
-                Ok(state.clone())
+            GameEvent::CommandViolated { .. } => Ok(state.clone()),
+            GameEvent::CommandRejected { mech, cmd, reason } => {
+                let rejection = CommandRejection {
+                    turn: state.turn_status.current,
+                    cmd: cmd.clone(),
+                    reason: reason.to_string(),
+                };
+                Ok(MatchState::modify_mech(state, mech, |m| {
+                    let mut m = m;
+                    m.rejections.push(rejection.clone());
+                    m
+                }))
             }
+            GameEvent::TurnTimedOut { mech, .. } => {
+                Ok(MatchState::modify_mech(state, mech, |m| MechState {
+                    timeouts: m.timeouts + 1,
+                    remaining_aps: 0,
+                    ..m
+                }))
+            }
+            GameEvent::ActionPointsExceeded { mech, .. } => {
+                Ok(MatchState::modify_mech(state, mech, |m| MechState {
+                    ap_violations: m.ap_violations + 1,
+                    ..m
+                }))
+            }
+            GameEvent::WeaponFired { mech, weapon } => {
+                Ok(MatchState::discharge_weapon(state, mech, *weapon))
+            }
+            GameEvent::WeaponFireRejected { .. } => Ok(state.clone()),
+            GameEvent::PenaltyApplied {
+                mech,
+                turn,
+                penalty,
+            } => Ok(MatchState::modify_mech(state, mech, |m| {
+                let mut penalties = m.penalties.clone();
+                penalties.push((*turn, penalty.clone()));
+                MechState {
+                    forfeit_next_turn: m.forfeit_next_turn
+                        || *penalty == Penalty::ActionPointsForfeited,
+                    penalties,
+                    ..m
+                }
+            })),
             GameEvent::DamageTaken {
                 damage_target,
                 damage,
@@ -364,57 +819,259 @@ impl Aggregate for Match {
             GameEvent::MechTurnCompleted { mech, .. } => {
                 Ok(MatchState::advance_mech_turn(state, mech))
             }
+            GameEvent::TurnOrderDetermined { order, .. } => {
+                Ok(MatchState::set_turn_order(state, order))
+            }
             GameEvent::GameFinished { cause } => Ok(MatchState::finish_game(state, cause)),
             GameEvent::RegisterUpdate { mech, reg, val } => {
                 Ok(MatchState::update_register(state, mech, reg, val))
             }
+            GameEvent::RegisterCleared { mech, reg } => {
+                Ok(MatchState::modify_mech(state, mech, |m| {
+                    let mut m = m;
+                    m.registers.remove(reg);
+                    m
+                }))
+            }
+            GameEvent::RegisterOperationRejected { .. } => Ok(state.clone()),
+            GameEvent::TerrainDamaged {
+                position, damage, ..
+            } => Ok(MatchState::damage_terrain(state, position, *damage)),
+            GameEvent::TerrainDestroyed { position, .. } => {
+                Ok(MatchState::remove_terrain(state, position))
+            }
+            GameEvent::PickupSpawned { pickup } => Ok(MatchState::add_pickup(state, pickup)),
+            GameEvent::PickupCollected { mech, pickup } => {
+                Ok(MatchState::collect_pickup(state, mech, pickup))
+            }
         }
     }
 
+    /// Commands that can't be carried out (e.g. for an unknown mech) are turned into a
+    /// rejection that the mech hears about on its next turn, rather than an error
     fn handle_command(state: &Self::State, cmd: &Self::Command) -> Result<Vec<Self::Event>> {
+        Ok(Self::try_command(state, cmd).unwrap_or_else(|e| {
+            vec![GameEvent::CommandRejected {
+                mech: cmd.mech().to_string(),
+                cmd: cmd.clone(),
+                reason: e.to_string(),
+            }]
+        }))
+    }
+}
+
+impl Match {
+    fn try_command(state: &MatchState, cmd: &MechCommand) -> Result<Vec<GameEvent>> {
         use MechCommand::*;
-        match cmd {
+        let evts = match cmd {
             Move { mech, .. }
+            | MoveTo { mech, .. }
             | FirePrimary { mech, .. }
             | FireSecondary { mech, .. }
             | RequestRadarScan { mech, .. }
-                if MatchState::validate_can_take_action(state, mech, cmd).is_err() =>
+            | Rotate { mech, .. }
+            | RaiseShield { mech, .. }
+            | Brace { mech, .. }
+                if MatchState::validate_has_mech(state, mech).is_ok()
+                    && MatchState::validate_can_take_action(state, mech, cmd).is_err() =>
             {
-                return Ok(vec![GameEvent::ActionPointsExceeded {
-                    mech: mech.to_string(),
-                    cmd: cmd.clone(),
-                }])
+                Ok(Self::handle_ap_exceeded(state, mech, cmd))
+            }
+            FirePrimary { mech, .. } | FireSecondary { mech, .. }
+                if MatchState::weapon_unavailable(state, mech, cmd).is_some() =>
+            {
+                Ok(Self::handle_weapon_unavailable(state, mech, cmd))
             }
             Move {
                 mech, direction, ..
             } => Self::handle_move(state, mech, direction, cmd),
+            MoveTo { mech, target, .. } => Self::handle_move_to(state, mech, target, cmd),
             FirePrimary {
                 mech, direction, ..
             } => Self::handle_fire_primary(state, mech, direction, cmd),
             FireSecondary {
                 mech, direction, ..
             } => Self::handle_fire_secondary(state, mech, direction, cmd),
-            RequestRadarScan { mech, .. } => Self::handle_radar(state, mech, cmd),
+            RequestRadarScan {
+                mech, direction, ..
+            } => Self::handle_radar(state, mech, direction, cmd),
+            Rotate {
+                mech, direction, ..
+            } => Self::handle_rotate(state, mech, direction, cmd),
+            RaiseShield { mech, .. } => Self::handle_defense(
+                state,
+                mech,
+                GameEvent::ShieldRaised {
+                    mech: mech.to_string(),
+                },
+                cmd,
+            ),
+            Brace { mech, .. } => Self::handle_defense(
+                state,
+                mech,
+                GameEvent::Braced {
+                    mech: mech.to_string(),
+                },
+                cmd,
+            ),
             SpawnMech {
                 mech,
                 position,
                 team,
                 avatar,
                 name,
-            } => Ok(vec![GameEvent::MechSpawned {
-                mech: mech.to_string(),
-                position: MatchState::nearest_unoccupied(state, &Some(position.clone())).unwrap(),
-                team: team.to_string(),
-                avatar: avatar.to_string(),
-                name: name.to_string(),
-            }]),
+            } => MatchState::nearest_unoccupied(state, &Some(position.clone()))
+                .ok_or_else(|| eventsourcing::Error {
+                    kind: eventsourcing::Kind::CommandFailure(
+                        "No unoccupied space left to spawn the mech".to_string(),
+                    ),
+                })
+                .map(|position| {
+                    vec![GameEvent::MechSpawned {
+                        mech: mech.to_string(),
+                        position,
+                        team: state.parameters.teams.get(mech).unwrap_or(team).to_string(),
+                        avatar: avatar.to_string(),
+                        name: name.to_string(),
+                    }]
+                }),
             FinishTurn { mech, turn } => Self::handle_turn_finish(state, mech, *turn),
             RegisterUpdate { .. } => Self::handle_register_update(state, cmd),
+        }?;
+        Ok(Self::check_game_over(state, evts))
+    }
+
+    /// Handles a command returned by the given actor, refusing (and recording) any
+    /// command issued for another mech or for a turn other than the current one
+    pub fn handle_actor_command(
+        state: &MatchState,
+        actor: &str,
+        cmd: &MechCommand,
+    ) -> Result<Vec<GameEvent>> {
+        match Self::command_violation(state, actor, cmd) {
+            Some(violation) => Ok(vec![GameEvent::CommandViolated {
+                actor: actor.to_string(),
+                cmd: cmd.clone(),
+                violation,
+            }]),
+            None => Self::handle_command(state, cmd),
+        }
+    }
+
+    fn command_violation(
+        state: &MatchState,
+        actor: &str,
+        cmd: &MechCommand,
+    ) -> Option<CommandViolation> {
+        match cmd.turn() {
+            None => Some(CommandViolation::Forbidden),
+            _ if cmd.mech() != actor => Some(CommandViolation::NotOwner),
+            Some(turn) if turn != state.turn_status.current => Some(CommandViolation::WrongTurn {
+                current: state.turn_status.current,
+            }),
+            _ => None,
+        }
+    }
+
+    fn handle_ap_exceeded(
+        state: &MatchState,
+        mech: &str,
+        cmd: &MechCommand,
+    ) -> Vec<<Match as Aggregate>::Event> {
+        let mut evts = vec![GameEvent::ActionPointsExceeded {
+            mech: mech.to_string(),
+            cmd: cmd.clone(),
+        }];
+        let offender = match state.mechs.get(mech) {
+            Some(m) if m.alive => m,
+            _ => return evts,
+        };
+        let penalty = |penalty| GameEvent::PenaltyApplied {
+            mech: mech.to_string(),
+            turn: state.turn_status.current,
+            penalty,
+        };
+        match &state.parameters.ruleset.ap_penalty {
+            ApPenaltyPolicy::Ignore => {}
+            ApPenaltyPolicy::HullDamage { damage } => {
+                evts.push(penalty(Penalty::HullDamage(*damage)));
+                evts.extend(Self::do_damage(
+                    state,
+                    DamageSource::Penalty,
+                    mech,
+                    *damage,
+                    offender.health,
+                ));
+            }
+            ApPenaltyPolicy::ForfeitNextTurn => {
+                evts.push(penalty(Penalty::ActionPointsForfeited));
+            }
+            ApPenaltyPolicy::Disqualify { after } => {
+                // this violation hasn't been counted yet
+                if offender.ap_violations + 1 >= *after {
+                    evts.push(penalty(Penalty::Disqualified));
+                    evts.push(Self::mech_destroyed(state, mech, DamageSource::Penalty));
+                }
+            }
+        }
+        evts
+    }
+
+    fn handle_weapon_unavailable(
+        state: &MatchState,
+        mech: &str,
+        cmd: &MechCommand,
+    ) -> Vec<<Match as Aggregate>::Event> {
+        match (
+            cmd.weapon(),
+            MatchState::weapon_unavailable(state, mech, cmd),
+        ) {
+            (Some(weapon), Some(reason)) => vec![GameEvent::WeaponFireRejected {
+                mech: mech.to_string(),
+                weapon,
+                reason,
+            }],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Decides the order in which mechs act during the given turn of a sequential match,
+    /// according to the match's turn order policy
+    pub fn begin_turn(state: &MatchState, turn: u32) -> Vec<GameEvent> {
+        vec![GameEvent::TurnOrderDetermined {
+            turn,
+            policy: state.parameters.turn_order.clone(),
+            order: MatchState::turn_order(state, turn),
+        }]
+    }
+
+    /// Starts a mech's own part of the current turn, just before it is asked for its
+    /// commands. Shields and braces raised on an earlier turn stay up until then
+    pub fn begin_mech_turn(state: &MatchState, mech: &str) -> Vec<GameEvent> {
+        let turn = state.turn_status.current;
+        match state.mechs.get(mech) {
+            Some(m)
+                if m.shield_raised.map_or(false, |t| t < turn)
+                    || m.braced.map_or(false, |t| t < turn) =>
+            {
+                vec![GameEvent::DefensesLowered {
+                    mech: mech.to_string(),
+                }]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Drops a new pickup onto the board if the match's pickup rules call for one at the
+    /// start of the given turn
+    pub fn spawn_pickups(state: &MatchState, turn: u32) -> Vec<GameEvent> {
+        if state.completed.is_some() {
+            Vec::new()
+        } else {
+            pickups::spawn_pickup(state, turn)
         }
     }
-}
 
-impl Match {
     fn handle_move(
         state: &<Match as Aggregate>::State,
         mech: &str,
@@ -422,36 +1079,81 @@ impl Match {
         cmd: &MechCommand,
     ) -> Result<Vec<<Match as Aggregate>::Event>> {
         MatchState::validate_has_mech(state, mech)?;
-        Ok(vec![
-            match state.mechs[mech]
-                .position
-                .relative_point(&state.game_board, dir, 1)
-            {
-                Some(p) => {
-                    if let Some(m) = MatchState::mech_at(state, &p) {
-                        GameEvent::DamageTaken {
-                            damage: WALL_DAMAGE,
-                            damage_source: DamageSource::MechCollision(m.name.to_string()),
-                            damage_target: mech.to_string(),
-                        }
-                    } else {
-                        GameEvent::PositionUpdated {
+        let health = state.mechs[mech].health;
+        let mut evts = match state.mechs[mech]
+            .position
+            .relative_point(&state.game_board, dir, 1)
+        {
+            Some(p) => {
+                if let Some(m) = MatchState::mech_at(state, &p) {
+                    Self::collision_damage(
+                        state,
+                        mech,
+                        DamageSource::MechCollision(m.name.to_string()),
+                    )
+                } else if state.game_board.obstacle_at(&p).is_some() {
+                    // Only pits can be reached, solid terrain is unreachable
+                    Self::do_damage(
+                        state,
+                        DamageSource::Pit,
+                        mech,
+                        state.parameters.ruleset.pit_damage,
+                        health,
+                    )
+                } else {
+                    let mut evts = vec![GameEvent::PositionUpdated {
+                        mech: mech.to_string(),
+                        position: p.clone(),
+                    }];
+                    if let Some(pickup) = MatchState::pickup_at(state, &p) {
+                        evts.push(GameEvent::PickupCollected {
                             mech: mech.to_string(),
-                            position: p,
-                        }
+                            pickup,
+                        });
                     }
+                    evts
                 }
-                None => GameEvent::DamageTaken {
-                    damage_target: mech.to_string(),
-                    damage: WALL_DAMAGE,
-                    damage_source: DamageSource::Wall,
-                },
-            },
-            GameEvent::ActionPointsConsumed {
-                mech: mech.to_string(),
-                points_consumed: cmd.action_points(),
-            },
-        ])
+            }
+            None => Self::collision_damage(state, mech, DamageSource::Wall),
+        };
+        evts.push(GameEvent::ActionPointsConsumed {
+            mech: mech.to_string(),
+            points_consumed: cmd.action_points(&state.parameters.ruleset),
+        });
+        Ok(evts)
+    }
+
+    fn handle_move_to(
+        state: &<Match as Aggregate>::State,
+        mech: &str,
+        target: &Point,
+        cmd: &MechCommand,
+    ) -> Result<Vec<<Match as Aggregate>::Event>> {
+        MatchState::validate_has_mech(state, mech)?;
+        let path = state
+            .game_board
+            .path(&state.mechs[mech].position, target)
+            .unwrap_or_default();
+        let mut evts = Vec::new();
+        let mut moved = state.clone();
+        for dir in path.iter() {
+            let m = &moved.mechs[mech];
+            if !m.alive || m.remaining_aps < cmd.action_points(&state.parameters.ruleset) {
+                break;
+            }
+            let step = Self::handle_move(&moved, mech, dir, cmd)?;
+            let collided = !step
+                .iter()
+                .any(|evt| matches!(evt, GameEvent::PositionUpdated { .. }));
+            moved = step
+                .iter()
+                .try_fold(moved, |state, evt| Self::apply_event(&state, evt))?;
+            evts.extend(step);
+            if collided {
+                break;
+            }
+        }
+        Ok(evts)
     }
 
     fn handle_turn_finish(
@@ -481,11 +1183,19 @@ impl Match {
             }
             // if completing this turn will bump the current turn to the max turns, then we're done
             if state.turn_status.current == state.parameters.max_turns - 1 {
-                evts.push(GameEvent::GameFinished {
-                    cause: EndCause::MaxTurnsCompleted {
-                        survivors: MatchState::remaining_alive(state),
-                    },
-                });
+                let survivors = MatchState::remaining_alive(state);
+                let cause = if MatchState::is_team_match(state) {
+                    let mut teams: Vec<_> = survivors
+                        .iter()
+                        .map(|m| state.mechs[m].team.to_string())
+                        .collect();
+                    teams.sort();
+                    teams.dedup();
+                    EndCause::TeamDraw { teams, survivors }
+                } else {
+                    EndCause::MaxTurnsCompleted { survivors }
+                };
+                evts.push(GameEvent::GameFinished { cause });
             }
             Ok(evts)
         }
@@ -498,10 +1208,14 @@ impl Match {
         cmd: &MechCommand,
     ) -> Result<Vec<<Match as Aggregate>::Event>> {
         MatchState::validate_has_mech(state, mech)?;
-        let mut evts = Vec::new();
-        let targets: Vec<_> = state.mechs[mech]
-            .position
-            .gather_points(&state.game_board, dir, PRIMARY_RANGE)
+        let mut evts = vec![GameEvent::WeaponFired {
+            mech: mech.to_string(),
+            weapon: WeaponType::Primary,
+        }];
+        let origin = &state.mechs[mech].position;
+        let range = state.parameters.ruleset.primary_range;
+        let path = origin.gather_points(&state.game_board, dir, range);
+        let targets: Vec<_> = path
             .iter()
             .filter_map(|(p, _d)| MatchState::mech_at(state, p))
             .collect();
@@ -510,13 +1224,19 @@ impl Match {
                 state,
                 DamageSource::MechWeapon(mech.to_string()),
                 &targets[0].id,
-                PRIMARY_DAMAGE,
+                state.parameters.ruleset.primary_damage,
                 targets[0].health,
             ));
+        } else if let Some(obstacle) = Self::obstruction(state, origin, dir, path.len(), range) {
+            evts.extend(Self::damage_terrain(
+                DamageSource::MechWeapon(mech.to_string()),
+                &obstacle,
+                state.parameters.ruleset.primary_damage,
+            ));
         }
         evts.push(GameEvent::ActionPointsConsumed {
             mech: mech.to_string(),
-            points_consumed: cmd.action_points(),
+            points_consumed: cmd.action_points(&state.parameters.ruleset),
         });
         Ok(evts)
     }
@@ -528,10 +1248,14 @@ impl Match {
         cmd: &MechCommand,
     ) -> Result<Vec<<Match as Aggregate>::Event>> {
         MatchState::validate_has_mech(state, mech)?;
-        let mut evts = Vec::new();
-        let targets: Vec<_> = state.mechs[mech]
-            .position
-            .gather_points(&state.game_board, dir, SECONDARY_RANGE)
+        let mut evts = vec![GameEvent::WeaponFired {
+            mech: mech.to_string(),
+            weapon: WeaponType::Secondary,
+        }];
+        let origin = &state.mechs[mech].position;
+        let range = state.parameters.ruleset.secondary_range;
+        let path = origin.gather_points(&state.game_board, dir, range);
+        let targets: Vec<_> = path
             .iter()
             .filter_map(|(p, _d)| MatchState::mech_at(state, p))
             .collect();
@@ -541,18 +1265,24 @@ impl Match {
                 state,
                 DamageSource::MechWeapon(mech.to_string()),
                 &targets[0].id,
-                SECONDARY_DAMAGE,
+                state.parameters.ruleset.secondary_damage,
                 targets[0].health,
             ));
             Some(targets[0].position.clone())
+        } else if let Some(obstacle) = Self::obstruction(state, origin, dir, path.len(), range) {
+            // Projectile struck terrain before reaching its full range
+            evts.extend(Self::damage_terrain(
+                DamageSource::MechWeapon(mech.to_string()),
+                &obstacle,
+                state.parameters.ruleset.secondary_damage,
+            ));
+            Some(obstacle.position)
+        } else if path.len() == range {
+            // Projectile flew unobstructed in target direction
+            path.last().map(|(p, _d)| p.clone())
         } else {
-            // Projectile flew unobstructed in target direction, which could
-            // be a point off the board
-            state.mechs[mech].position.relative_point(
-                &state.game_board,
-                dir,
-                SECONDARY_RANGE as i32,
-            )
+            // Projectile flew off the board
+            None
         };
 
         // landing zone could've been off the board
@@ -568,7 +1298,7 @@ impl Match {
                             state,
                             DamageSource::MechWeapon(mech.to_string()),
                             &m.id,
-                            SECONDARY_SPLASH_DAMAGE,
+                            state.parameters.ruleset.secondary_splash_damage,
                             m.health,
                         )
                     })
@@ -578,7 +1308,7 @@ impl Match {
 
         evts.push(GameEvent::ActionPointsConsumed {
             mech: mech.to_string(),
-            points_consumed: cmd.action_points(),
+            points_consumed: cmd.action_points(&state.parameters.ruleset),
         });
         Ok(evts)
     }
@@ -591,25 +1321,263 @@ impl Match {
         remaining_health: u32,
     ) -> Vec<<Match as Aggregate>::Event> {
         let mut evts = Vec::new();
+        let amt = match &from {
+            DamageSource::MechWeapon(attacker) if MatchState::teammates(state, attacker, to) => {
+                match state.parameters.ruleset.friendly_fire {
+                    FriendlyFire::Full => amt,
+                    FriendlyFire::Reduced { percent } => {
+                        let reduced = u64::from(amt) * u64::from(percent) / 100;
+                        u32::try_from(reduced).unwrap_or(u32::MAX)
+                    }
+                    FriendlyFire::Off => return evts,
+                }
+            }
+            _ => amt,
+        };
+        let shielded = state
+            .mechs
+            .get(to)
+            .map_or(false, |m| m.shield_raised.is_some());
+        let blocked = match &from {
+            DamageSource::Penalty => 0,
+            _ if shielded => {
+                let absorption = state.parameters.ruleset.shield_absorption.min(100);
+                (u64::from(amt) * u64::from(absorption) / 100) as u32
+            }
+            _ => 0,
+        };
+        if blocked > 0 {
+            evts.push(GameEvent::DamageBlocked {
+                mech: to.to_string(),
+                damage: blocked,
+                damage_source: from.clone(),
+            });
+        }
+        let amt = amt - blocked;
         evts.push(GameEvent::DamageTaken {
             damage: amt,
             damage_source: from.clone(),
             damage_target: to.to_string(),
         });
         if amt >= remaining_health {
-            evts.push(GameEvent::MechDestroyed {
-                damage_target: to.to_string(),
+            evts.push(Self::mech_destroyed(state, to, from));
+        }
+        evts
+    }
+
+    fn mech_destroyed(state: &MatchState, mech: &str, damage_source: DamageSource) -> GameEvent {
+        let friendly_fire = match &damage_source {
+            DamageSource::MechWeapon(attacker) => MatchState::teammates(state, attacker, mech),
+            _ => false,
+        };
+        GameEvent::MechDestroyed {
+            damage_target: mech.to_string(),
+            damage_source,
+            friendly_fire,
+        }
+    }
+
+    /// Ends the match if the mechs destroyed by a command leave at most one mech
+    /// (or team) standing
+    fn check_game_over(
+        state: &MatchState,
+        evts: Vec<<Match as Aggregate>::Event>,
+    ) -> Vec<<Match as Aggregate>::Event> {
+        let finished = evts.iter().any(|evt| match evt {
+            GameEvent::GameFinished { .. } => true,
+            _ => false,
+        });
+        if state.completed.is_some() || finished {
+            return evts;
+        }
+        let mut destroyed = Vec::new();
+        let mut disqualified = Vec::new();
+        for evt in evts.iter() {
+            match evt {
+                GameEvent::MechDestroyed { damage_target, .. }
+                    if state.mechs.get(damage_target).map_or(false, |m| m.alive)
+                        && !destroyed.contains(damage_target) =>
+                {
+                    destroyed.push(damage_target.to_string())
+                }
+                GameEvent::PenaltyApplied {
+                    mech,
+                    penalty: Penalty::Disqualified,
+                    ..
+                }
+                | GameEvent::PenaltyApplied {
+                    mech,
+                    penalty: Penalty::Ejected,
+                    ..
+                } => disqualified.push(mech.to_string()),
+                _ => {}
+            }
+        }
+        let mut evts = evts;
+        if let Some(cause) = Self::game_over(state, &destroyed, &disqualified) {
+            // Game Over
+            evts.push(GameEvent::GameFinished { cause });
+        }
+        evts
+    }
+
+    /// Decides how the match ends (if it does) once the given mechs are out of play.
+    /// Winning only because the other mechs were disqualified or ejected is a win by forfeit
+    fn game_over(
+        state: &MatchState,
+        destroyed: &[String],
+        disqualified: &[String],
+    ) -> Option<EndCause> {
+        if destroyed.is_empty() {
+            return None;
+        }
+        let survivors: Vec<_> = Self::living_mechs(state)
+            .into_iter()
+            .filter(|m| !destroyed.contains(m))
+            .collect();
+        if survivors.is_empty() {
+            let mut mechs = destroyed.to_vec();
+            mechs.sort();
+            return Some(EndCause::Draw { mechs });
+        }
+        match Self::victory(state, &survivors) {
+            Some(EndCause::MechVictory(victor))
+                if destroyed.iter().all(|m| disqualified.contains(m)) =>
+            {
+                let mut forfeited = destroyed.to_vec();
+                forfeited.sort();
+                Some(EndCause::Forfeit { victor, forfeited })
+            }
+            cause => cause,
+        }
+    }
+
+    /// Forfeits the current turn for a mech that didn't respond in time, ejecting it from
+    /// the match once it has timed out on too many turns
+    pub fn time_out(state: &MatchState, mech: &str) -> Vec<GameEvent> {
+        let timeouts = match state.mechs.get(mech) {
+            Some(m) if m.alive && state.completed.is_none() => m.timeouts,
+            _ => return vec![],
+        };
+        let turn = state.turn_status.current;
+        let mut evts = vec![GameEvent::TurnTimedOut {
+            mech: mech.to_string(),
+            turn,
+        }];
+        let max_timeouts = state.parameters.ruleset.max_timeouts;
+        // this timeout hasn't been counted yet
+        if max_timeouts > 0 && timeouts + 1 >= max_timeouts {
+            evts.push(GameEvent::PenaltyApplied {
+                mech: mech.to_string(),
+                turn,
+                penalty: Penalty::Ejected,
+            });
+            evts.push(Self::mech_destroyed(state, mech, DamageSource::Penalty));
+        }
+        Self::check_game_over(state, evts)
+    }
+
+    /// Ends the match for a reason outside of play, such as an administrator aborting it
+    pub fn end_match(state: &MatchState, cause: EndCause) -> Vec<GameEvent> {
+        if state.completed.is_some() {
+            vec![]
+        } else {
+            vec![GameEvent::GameFinished { cause }]
+        }
+    }
+
+    /// Ends the match when mechs still in play have lost their connection to the arena.
+    /// The match is forfeited to the last connected mech (or team), or ends without a
+    /// result when no mechs in play are connected at all
+    pub fn check_connections(state: &MatchState, connected: &[String]) -> Vec<GameEvent> {
+        let mut living = Self::living_mechs(state);
+        living.sort();
+        let (present, missing): (Vec<_>, Vec<_>) =
+            living.into_iter().partition(|m| connected.contains(m));
+        let cause = if missing.is_empty() {
+            None
+        } else if present.is_empty() {
+            Some(EndCause::AllMechsDisconnected)
+        } else {
+            match Self::victory(state, &present) {
+                Some(EndCause::MechVictory(victor)) => Some(EndCause::Forfeit {
+                    victor,
+                    forfeited: missing,
+                }),
+                cause => cause,
+            }
+        };
+        cause.map_or(vec![], |cause| Self::end_match(state, cause))
+    }
+
+    /// Decides whether the surviving mechs have won the match. The last mech standing
+    /// wins, or in a team match the last team standing
+    fn victory(state: &MatchState, survivors: &[String]) -> Option<EndCause> {
+        let mut survivors = survivors.to_vec();
+        survivors.sort();
+        if MatchState::is_team_match(state) {
+            let mut teams: Vec<_> = survivors.iter().map(|m| &state.mechs[m].team).collect();
+            teams.sort();
+            teams.dedup();
+            if teams.len() == 1 {
+                let team = teams[0].to_string();
+                let mut losers: Vec<_> = state
+                    .mechs
+                    .values()
+                    .filter(|m| m.team != team)
+                    .map(|m| m.id.to_string())
+                    .collect();
+                losers.sort();
+                return Some(EndCause::TeamVictory {
+                    team,
+                    survivors,
+                    losers,
+                });
+            }
+        } else if survivors.len() == 1 {
+            return Some(EndCause::MechVictory(survivors[0].to_string()));
+        }
+        None
+    }
+
+    /// Returns the solid obstacle (if any) that stopped a projectile after it travelled
+    /// the given distance, short of its full range
+    fn obstruction(
+        state: &MatchState,
+        origin: &Point,
+        dir: &GridDirection,
+        travelled: usize,
+        range: usize,
+    ) -> Option<Obstacle> {
+        if travelled >= range {
+            None
+        } else {
+            state
+                .game_board
+                .obstacle_at(&origin.offset(dir, travelled as i32 + 1))
+                .filter(|o| o.terrain.is_solid())
+                .cloned()
+        }
+    }
+
+    fn damage_terrain(
+        from: DamageSource,
+        obstacle: &Obstacle,
+        amt: u32,
+    ) -> Vec<<Match as Aggregate>::Event> {
+        let mut evts = Vec::new();
+        // Walls absorb any amount of damage
+        if let TerrainType::Cover { health } = obstacle.terrain {
+            evts.push(GameEvent::TerrainDamaged {
+                position: obstacle.position.clone(),
+                damage: amt,
                 damage_source: from.clone(),
             });
-            let remaining_mechs = Self::living_mechs(state)
-                .into_iter()
-                .filter(|m| m != &to)
-                .collect::<Vec<_>>();
-            if remaining_mechs.len() == 1 {
-                // Game Over
-                evts.push(GameEvent::GameFinished {
-                    cause: EndCause::MechVictory(remaining_mechs[0].clone()),
-                })
+            if amt >= health {
+                evts.push(GameEvent::TerrainDestroyed {
+                    position: obstacle.position.clone(),
+                    damage_source: from,
+                });
             }
         }
         evts
@@ -627,10 +1595,15 @@ impl Match {
     fn handle_radar(
         state: &<Match as Aggregate>::State,
         mech: &str,
+        direction: &Option<GridDirection>,
         cmd: &MechCommand,
     ) -> Result<Vec<<Match as Aggregate>::Event>> {
-        let pings =
-            crate::radar::radar_ping(state, &state.mechs[mech].position, &state.mechs[mech].team);
+        MatchState::validate_has_mech(state, mech)?;
+        let origin = &state.mechs[mech].position;
+        let mut pings = crate::radar::radar_ping(state, origin, &state.mechs[mech].team, direction);
+        if let Some((facing, arc)) = MatchState::facing_arc(state, mech) {
+            pings.retain(|p| radar::within_arc(origin, &p.location, &facing, arc));
+        }
         Ok(vec![
             GameEvent::RadarScanCompleted {
                 actor: mech.to_string(),
@@ -638,61 +1611,474 @@ impl Match {
             },
             GameEvent::ActionPointsConsumed {
                 mech: mech.to_string(),
-                points_consumed: cmd.action_points(),
+                points_consumed: cmd.action_points(&state.parameters.ruleset),
+            },
+        ])
+    }
+
+    fn handle_rotate(
+        state: &<Match as Aggregate>::State,
+        mech: &str,
+        direction: &GridDirection,
+        cmd: &MechCommand,
+    ) -> Result<Vec<<Match as Aggregate>::Event>> {
+        MatchState::validate_has_mech(state, mech)?;
+        Ok(vec![
+            GameEvent::FacingChanged {
+                mech: mech.to_string(),
+                facing: *direction,
+            },
+            GameEvent::ActionPointsConsumed {
+                mech: mech.to_string(),
+                points_consumed: cmd.action_points(&state.parameters.ruleset),
+            },
+        ])
+    }
+
+    fn handle_defense(
+        state: &<Match as Aggregate>::State,
+        mech: &str,
+        evt: GameEvent,
+        cmd: &MechCommand,
+    ) -> Result<Vec<<Match as Aggregate>::Event>> {
+        MatchState::validate_has_mech(state, mech)?;
+        Ok(vec![
+            evt,
+            GameEvent::ActionPointsConsumed {
+                mech: mech.to_string(),
+                points_consumed: cmd.action_points(&state.parameters.ruleset),
             },
         ])
     }
 
+    /// Damage from bouncing off walls or other mechs, which a braced mech shrugs off
+    fn collision_damage(
+        state: &MatchState,
+        mech: &str,
+        damage_source: DamageSource,
+    ) -> Vec<GameEvent> {
+        let damage = state.parameters.ruleset.wall_damage;
+        match state.mechs.get(mech) {
+            Some(m) if m.braced.is_some() => vec![GameEvent::DamageBlocked {
+                mech: mech.to_string(),
+                damage,
+                damage_source,
+            }],
+            Some(m) => Self::do_damage(state, damage_source, mech, damage, m.health),
+            None => Vec::new(),
+        }
+    }
+
     fn handle_register_update(
         state: &<Match as Aggregate>::State,
         cmd: &MechCommand,
     ) -> Result<Vec<<Match as Aggregate>::Event>> {
-        let event = if let MechCommand::RegisterUpdate { mech, reg, op, .. } = cmd {
-            let curr_val = state.mechs[mech].registers.get(reg);
-            match op {
-                RegisterOperation::Accumulate(acc) if *reg == EAX || *reg == ECX => {
-                    if let Some(RegisterValue::Number(n)) = curr_val {
-                        vec![GameEvent::RegisterUpdate {
-                            mech: mech.to_string(),
-                            reg: reg.clone(),
-                            val: RegisterValue::Number(n.saturating_add(*acc)),
-                        }]
-                    } else {
-                        vec![]
-                    }
+        use RegisterOperation::*;
+        let (mech, reg, op) = match cmd {
+            MechCommand::RegisterUpdate { mech, reg, op, .. } => (mech, *reg, op),
+            _ => return Ok(vec![]),
+        };
+        MatchState::validate_has_mech(state, mech)?;
+        let kind = MatchState::validate_register(state, reg, op)?;
+        let registers = &state.mechs[mech].registers;
+        let update = |reg: u32, val: RegisterValue| GameEvent::RegisterUpdate {
+            mech: mech.to_string(),
+            reg,
+            val,
+        };
+        let cleared = |reg: u32| GameEvent::RegisterCleared {
+            mech: mech.to_string(),
+            reg,
+        };
+        let number = match registers.get(&reg) {
+            Some(RegisterValue::Number(n)) => *n,
+            _ => 0,
+        };
+        let other_kind = |other: &u32| state.parameters.ruleset.registers.kind(*other);
+        let outcome = match op {
+            Accumulate(_) | Decrement(_) | Multiply(_) | Modulo(_)
+                if kind != RegisterKind::Number =>
+            {
+                Err(RegisterFault::WrongKind { kind })
+            }
+            Accumulate(acc) => Ok(vec![update(
+                reg,
+                RegisterValue::Number(number.saturating_add(*acc)),
+            )]),
+            Decrement(dec) => Ok(vec![update(
+                reg,
+                RegisterValue::Number(number.saturating_sub(*dec)),
+            )]),
+            Multiply(factor) => Ok(vec![update(
+                reg,
+                RegisterValue::Number(number.saturating_mul(*factor)),
+            )]),
+            Modulo(0) => Err(RegisterFault::DivisionByZero),
+            Modulo(divisor) => Ok(vec![update(reg, RegisterValue::Number(number % divisor))]),
+            Set(val) if val.kind() != kind => Err(RegisterFault::WrongKind { kind }),
+            Set(val) => Ok(vec![update(reg, val.clone())]),
+            CompareAndSet { expected, new } if expected.kind() != kind || new.kind() != kind => {
+                Err(RegisterFault::WrongKind { kind })
+            }
+            CompareAndSet { expected, new } => {
+                if registers.get(&reg) == Some(expected) {
+                    Ok(vec![update(reg, new.clone())])
+                } else {
+                    Err(RegisterFault::Mismatch)
                 }
-                RegisterOperation::Decrement(dec) if *reg == EAX || *reg == ECX => {
-                    if let Some(RegisterValue::Number(n)) = curr_val {
-                        vec![GameEvent::RegisterUpdate {
-                            mech: mech.to_string(),
-                            reg: reg.clone(),
-                            val: RegisterValue::Number(n.saturating_sub(*dec)),
-                        }]
-                    } else {
-                        vec![]
+            }
+            Copy { from: other } | Swap { with: other } if other_kind(other) != Some(kind) => {
+                Err(RegisterFault::WrongKind {
+                    kind: other_kind(other).unwrap_or(kind),
+                })
+            }
+            Copy { from } => match registers.get(from) {
+                Some(val) => Ok(vec![update(reg, val.clone())]),
+                None => Err(RegisterFault::Empty { reg: *from }),
+            },
+            Swap { with } => Ok(vec![(reg, *with), (*with, reg)]
+                .into_iter()
+                .map(|(dest, src)| match registers.get(&src) {
+                    Some(val) => update(dest, val.clone()),
+                    None => cleared(dest),
+                })
+                .collect()),
+            Clear => Ok(vec![cleared(reg)]),
+        };
+        Ok(outcome.unwrap_or_else(|fault| {
+            vec![GameEvent::RegisterOperationRejected {
+                mech: mech.to_string(),
+                reg,
+                op: op.clone(),
+                fault,
+            }]
+        }))
+    }
+
+    /// Resolves the commands every mech submitted for a turn planned from the same snapshot
+    /// of the match. Commands are resolved in rounds made up of at most one command from
+    /// each mech: all moves in a round happen at once, a step at a time, then weapons and
+    /// radar are resolved against the new positions. Mechs and cover are only destroyed
+    /// once the whole round has been resolved, so mechs can destroy each other. Turn
+    /// completions are handled after the last round. Each event is returned alongside the mech it is attributed to
+    pub fn resolve_simultaneous(
+        state: &MatchState,
+        orders: &[(String, Vec<MechCommand>)],
+    ) -> Result<Vec<(String, GameEvent)>> {
+        let mut orders = orders.to_vec();
+        orders.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut actions = Vec::new();
+        let mut finishes = Vec::new();
+        let mut evts = Vec::new();
+        for (actor, cmds) in orders.iter() {
+            let (f, a): (Vec<_>, Vec<_>) = cmds
+                .iter()
+                .filter(|cmd| match Self::command_violation(state, actor, cmd) {
+                    Some(violation) => {
+                        evts.push((
+                            actor.to_string(),
+                            GameEvent::CommandViolated {
+                                actor: actor.to_string(),
+                                cmd: (*cmd).clone(),
+                                violation,
+                            },
+                        ));
+                        false
                     }
+                    None => true,
+                })
+                .partition(|cmd| matches!(cmd, MechCommand::FinishTurn { .. }));
+            actions.push((actor.as_str(), a));
+            finishes.extend(f.into_iter().map(|cmd| (actor.as_str(), cmd)));
+        }
+
+        let mut state = state.clone();
+        let rounds = actions.iter().map(|(_, a)| a.len()).max().unwrap_or(0);
+        for round in 0..rounds {
+            if state.completed.is_some() {
+                break;
+            }
+            // Mechs destroyed in an earlier round don't get to carry out the rest of their orders
+            let cmds: Vec<_> = actions
+                .iter()
+                .filter(|(actor, _)| state.mechs.get(*actor).map_or(false, |m| m.alive))
+                .filter_map(|(actor, a)| a.get(round).map(|cmd| (*actor, *cmd)))
+                .collect();
+            let round_evts = Self::resolve_round(&state, &cmds)?;
+            state = Self::apply_all(&state, &round_evts)?;
+            evts.extend(round_evts);
+        }
+        for (actor, cmd) in finishes {
+            let finish_evts = Self::resolve_command(&state, actor, cmd);
+            state = Self::apply_all(&state, &finish_evts)?;
+            evts.extend(finish_evts);
+        }
+        Ok(evts)
+    }
+
+    /// Handles one mech's command during a simultaneous turn. A command that can't be
+    /// carried out is rejected for that mech alone, so the rest of the turn still resolves
+    fn resolve_command(
+        state: &MatchState,
+        actor: &str,
+        cmd: &MechCommand,
+    ) -> Vec<(String, GameEvent)> {
+        Self::handle_command(state, cmd)
+            .and_then(|evts| {
+                evts.iter()
+                    .try_fold(state.clone(), |state, evt| Self::apply_event(&state, evt))
+                    .map(|_| evts)
+            })
+            .unwrap_or_else(|e| {
+                vec![GameEvent::CommandRejected {
+                    mech: cmd.mech().to_string(),
+                    cmd: cmd.clone(),
+                    reason: e.to_string(),
+                }]
+            })
+            .into_iter()
+            .map(|evt| (actor.to_string(), evt))
+            .collect()
+    }
+
+    fn resolve_round(
+        state: &MatchState,
+        cmds: &[(&str, &MechCommand)],
+    ) -> Result<Vec<(String, GameEvent)>> {
+        let mut evts = Vec::new();
+        let moved = Self::resolve_moves(state, cmds, &mut evts)?;
+        for (actor, cmd) in cmds.iter().filter(|(_, cmd)| !Self::is_move(cmd)) {
+            for (actor, evt) in Self::resolve_command(&moved, actor, cmd) {
+                match evt {
+                    // Destruction is decided once all of the round's damage has been dealt
+                    GameEvent::MechDestroyed { .. }
+                    | GameEvent::TerrainDestroyed { .. }
+                    | GameEvent::GameFinished { .. } => {}
+                    evt => evts.push((actor, evt)),
                 }
-                RegisterOperation::Set(v) => match v {
-                    RegisterValue::Number(_n) if *reg == EAX || *reg == ECX => {
-                        vec![GameEvent::RegisterUpdate {
-                            mech: mech.to_string(),
-                            reg: reg.clone(),
-                            val: v.clone(),
-                        }]
+            }
+        }
+
+        let resolved = Self::apply_all(state, &evts)?;
+        let casualties = Self::round_casualties(state, &resolved, &evts);
+        evts.extend(casualties);
+        Ok(evts)
+    }
+
+    fn is_move(cmd: &MechCommand) -> bool {
+        matches!(cmd, MechCommand::Move { .. } | MechCommand::MoveTo { .. })
+    }
+
+    /// Moves every mech moving this round one step at a time, so that mechs following a
+    /// path collide with mechs heading for the same space at the same step. A mech stops
+    /// following its path once it collides or runs out of action points. Returns the match
+    /// with every mech moved
+    fn resolve_moves(
+        state: &MatchState,
+        cmds: &[(&str, &MechCommand)],
+        evts: &mut Vec<(String, GameEvent)>,
+    ) -> Result<MatchState> {
+        let paths: Vec<(&str, &MechCommand, Vec<MechCommand>)> = cmds
+            .iter()
+            .filter(|(_, cmd)| Self::is_move(cmd))
+            .map(|(actor, cmd)| (*actor, *cmd, Self::move_steps(state, cmd)))
+            .collect();
+        let longest = paths.iter().map(|(_, _, steps)| steps.len()).max();
+        let mut moved = state.clone();
+        let mut stopped: Vec<&str> = Vec::new();
+        for i in 0..longest.unwrap_or(0) {
+            let mut step_evts = Vec::new();
+            let mut stepping = Vec::new();
+            for (actor, cmd, steps) in paths.iter() {
+                let step = match steps.get(i) {
+                    Some(step) if !stopped.contains(actor) => step,
+                    _ => continue,
+                };
+                let walking = matches!(cmd, MechCommand::MoveTo { .. });
+                if walking && !moved.mechs.get(*actor).map_or(false, |m| m.alive) {
+                    stopped.push(actor);
+                    continue;
+                }
+                if walking && MatchState::validate_can_take_action(&moved, actor, step).is_err() {
+                    // the first step is charged for like any other command
+                    if i == 0 {
+                        step_evts.extend(Self::resolve_command(&moved, actor, cmd));
                     }
-                    RegisterValue::Text(_s) if *reg == EBX => vec![GameEvent::RegisterUpdate {
-                        mech: mech.to_string(),
-                        reg: reg.clone(),
-                        val: v.clone(),
-                    }],
-                    _ => vec![],
-                },
-                _ => vec![],
+                    stopped.push(actor);
+                    continue;
+                }
+                stepping.push(*actor);
+                step_evts.extend(Self::resolve_command(&moved, actor, step));
             }
-        } else {
-            vec![]
+            let mut step_evts = Self::resolve_move_conflicts(&moved, step_evts);
+            // Whether the match is over is decided once all of the round's damage has been dealt
+            step_evts.retain(|(_, evt)| !matches!(evt, GameEvent::GameFinished { .. }));
+            for actor in stepping {
+                let stepped = step_evts.iter().any(|(_, evt)| {
+                    matches!(evt, GameEvent::PositionUpdated { mech, .. } if mech == actor)
+                });
+                if !stepped {
+                    stopped.push(actor);
+                }
+            }
+            moved = Self::apply_all(&moved, &step_evts)?;
+            evts.extend(step_evts);
+        }
+        Ok(moved)
+    }
+
+    /// Breaks a movement command into the single steps it takes from the current snapshot
+    fn move_steps(state: &MatchState, cmd: &MechCommand) -> Vec<MechCommand> {
+        match cmd {
+            MechCommand::MoveTo { turn, mech, target } => state
+                .mechs
+                .get(mech)
+                .and_then(|m| state.game_board.path(&m.position, target))
+                .unwrap_or_default()
+                .into_iter()
+                .map(|direction| MechCommand::Move {
+                    turn: *turn,
+                    mech: mech.to_string(),
+                    direction,
+                })
+                .collect(),
+            _ => vec![cmd.clone()],
+        }
+    }
+
+    /// Mechs trying to move into the same space in the same step collide with each other
+    /// and stay where they are, leaving any pickup in that space where it lies
+    fn resolve_move_conflicts(
+        state: &MatchState,
+        evts: Vec<(String, GameEvent)>,
+    ) -> Vec<(String, GameEvent)> {
+        let destinations: Vec<(String, Point)> = evts
+            .iter()
+            .filter_map(|(_, evt)| match evt {
+                GameEvent::PositionUpdated { mech, position } => {
+                    Some((mech.to_string(), position.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        let collision = |mech: &str, position: &Point| {
+            destinations
+                .iter()
+                .find(|(other, p)| other != mech && p == position)
+                .map(|(other, _)| other.to_string())
         };
-        Ok(event)
+        evts.iter()
+            .flat_map(|(actor, evt)| {
+                let evts = match evt {
+                    GameEvent::PositionUpdated { mech, position } => {
+                        match collision(mech, position) {
+                            Some(other) => Self::collision_damage(
+                                state,
+                                mech,
+                                DamageSource::MechCollision(state.mechs[&other].name.to_string()),
+                            ),
+                            None => vec![evt.clone()],
+                        }
+                    }
+                    GameEvent::PickupCollected { mech, pickup }
+                        if collision(mech, &pickup.position).is_some() =>
+                    {
+                        Vec::new()
+                    }
+                    _ => vec![evt.clone()],
+                };
+                evts.into_iter().map(move |evt| (actor.to_string(), evt))
+            })
+            .collect()
+    }
+
+    /// Destroys the mechs and cover worn down during a round, ending the match if
+    /// at most one mech is left standing. Mechs already destroyed while moving, such as
+    /// those disqualified for overspending, aren't destroyed a second time
+    fn round_casualties(
+        before: &MatchState,
+        after: &MatchState,
+        evts: &[(String, GameEvent)],
+    ) -> Vec<(String, GameEvent)> {
+        let mut casualties = Vec::new();
+        let mut destroyed = Vec::new();
+        let mut disqualified = Vec::new();
+        let mut candidates = Self::living_mechs(before);
+        candidates.sort();
+        for mech in candidates {
+            let was_disqualified = evts.iter().any(|(_, evt)| match evt {
+                GameEvent::PenaltyApplied {
+                    mech: offender,
+                    penalty: Penalty::Disqualified,
+                    ..
+                } => *offender == mech,
+                _ => false,
+            });
+            let last_hit = evts.iter().rev().find_map(|(_, evt)| match evt {
+                GameEvent::DamageTaken {
+                    damage_target,
+                    damage_source,
+                    ..
+                } if *damage_target == mech => Some(damage_source.clone()),
+                _ => None,
+            });
+            let cause = if was_disqualified {
+                disqualified.push(mech.to_string());
+                Some(DamageSource::Penalty)
+            } else if after.mechs[&mech].health == 0 {
+                last_hit
+            } else {
+                None
+            };
+            let already_destroyed = evts.iter().any(|(_, evt)| {
+                matches!(evt, GameEvent::MechDestroyed { damage_target, .. } if *damage_target == mech)
+            });
+            if let Some(damage_source) = cause {
+                if !already_destroyed {
+                    casualties.push((
+                        mech.to_string(),
+                        Self::mech_destroyed(before, &mech, damage_source),
+                    ));
+                }
+                destroyed.push(mech);
+            }
+        }
+
+        for obstacle in after.game_board.terrain.iter() {
+            if obstacle.terrain != (TerrainType::Cover { health: 0 }) {
+                continue;
+            }
+            let last_hit = evts.iter().rev().find_map(|(actor, evt)| match evt {
+                GameEvent::TerrainDamaged {
+                    position,
+                    damage_source,
+                    ..
+                } if *position == obstacle.position => Some((actor, damage_source.clone())),
+                _ => None,
+            });
+            if let Some((actor, damage_source)) = last_hit {
+                casualties.push((
+                    actor.to_string(),
+                    GameEvent::TerrainDestroyed {
+                        position: obstacle.position.clone(),
+                        damage_source,
+                    },
+                ));
+            }
+        }
+
+        if after.completed.is_none() {
+            if let Some(cause) = Self::game_over(after, &destroyed, &disqualified) {
+                casualties.push((destroyed[0].to_string(), GameEvent::GameFinished { cause }));
+            }
+        }
+        casualties
+    }
+
+    fn apply_all(state: &MatchState, evts: &[(String, GameEvent)]) -> Result<MatchState> {
+        evts.iter().try_fold(state.clone(), |state, (_, evt)| {
+            Self::apply_event(&state, evt)
+        })
     }
 }
 
@@ -701,17 +2087,34 @@ mod test {
     use super::*;
     use crate::eventsourcing::Aggregate;
     use crate::radar;
-    use crate::radar::RadarPing;
-
-    fn gen_root_state(mechs: Vec<(&str, Point)>, max_turns: u32) -> MatchState {
-        let mut state = MatchState::new_with_parameters(MatchParameters {
+    use crate::radar::{PingKind, RadarPing};
+    use crate::{
+        numeric_register, text_register, PickupKind, PickupRules, RadarPattern, RegisterFile,
+        Ruleset, EAX, EBX, ECX, MEM,
+    };
+
+    fn gen_parameters(mechs: &[(&str, Point)], max_turns: u32) -> MatchParameters {
+        MatchParameters {
             actors: mechs.iter().map(|(a, _p)| a.to_string()).collect(),
             match_id: "test_match".to_string(),
             max_turns: max_turns,
             aps_per_turn: 4,
             height: 24,
             width: 24,
-        });
+            ..Default::default()
+        }
+    }
+
+    fn gen_root_state(mechs: Vec<(&str, Point)>, max_turns: u32) -> MatchState {
+        let params = gen_parameters(&mechs, max_turns);
+        gen_root_state_with_parameters(mechs, params)
+    }
+
+    fn gen_root_state_with_parameters(
+        mechs: Vec<(&str, Point)>,
+        params: MatchParameters,
+    ) -> MatchState {
+        let mut state = MatchState::new_with_parameters(params);
 
         for (mech, position) in mechs {
             let cmd = MechCommand::SpawnMech {
@@ -728,8 +2131,23 @@ mod test {
         state
     }
 
-    #[test]
-    fn basic_spawn() {
+    fn gen_ruleset_state(mechs: Vec<(&str, Point)>, ruleset: Ruleset) -> MatchState {
+        let params = MatchParameters {
+            ruleset,
+            ..gen_parameters(&mechs, 10)
+        };
+        gen_root_state_with_parameters(mechs, params)
+    }
+
+    fn apply_command(state: MatchState, cmd: &MechCommand) -> MatchState {
+        Match::handle_command(&state, cmd)
+            .unwrap()
+            .iter()
+            .fold(state, |state, evt| Match::apply_event(&state, evt).unwrap())
+    }
+
+    #[test]
+    fn basic_spawn() {
         let state = gen_root_state(
             vec![("bob", Point::new(10, 10)), ("alfred", Point::new(20, 20))],
             10,
@@ -802,6 +2220,35 @@ mod test {
         );
     }
 
+    #[test]
+    fn primary_fire_uses_ruleset() {
+        let mechs = vec![
+            ("shooter", Point::new(10, 6)),
+            ("victim", Point::new(11, 7)),
+            ("bystander", Point::new(12, 8)),
+        ];
+        let params = MatchParameters {
+            ruleset: Ruleset {
+                primary_damage: 250,
+                primary_range: 1,
+                ..Default::default()
+            },
+            ..gen_parameters(&mechs, 10)
+        };
+        let state = gen_root_state_with_parameters(mechs, params);
+
+        let cmd = MechCommand::FirePrimary {
+            turn: 0,
+            mech: "shooter".to_string(),
+            direction: GridDirection::NorthEast,
+        };
+
+        let state = apply_command(state, &cmd);
+
+        assert_eq!(state.mechs["victim"].health, INITIAL_HEALTH - 250);
+        assert_eq!(state.mechs["bystander"].health, INITIAL_HEALTH);
+    }
+
     #[test]
     fn secondary_fire() {
         let state = gen_root_state(
@@ -908,8 +2355,15 @@ mod test {
                 .fold(state, |state, evt| Match::apply_event(&state, evt).unwrap())
         });
         // this state should now not accept another turn from Al...
-        let evt = Match::handle_command(&state, &al2);
-        assert!(evt.is_err());
+        let evts = Match::handle_command(&state, &al2).unwrap();
+        match &evts[..] {
+            [GameEvent::CommandRejected { mech, .. }] => assert_eq!(mech, "al"),
+            evts => panic!("unexpected events {:?}", evts),
+        }
+        let state = evts
+            .iter()
+            .fold(state, |state, evt| Match::apply_event(&state, evt).unwrap());
+        assert_eq!(state.mechs["al"].rejections.len(), 1);
     }
 
     #[test]
@@ -1044,7 +2498,7 @@ mod test {
             10,
         );
         let origin = Point::new(10, 6); // al's position
-        let results = radar::collect_targets(&state, &origin);
+        let results = radar::collect_targets(&state, &origin, &None);
         assert_eq!(results.len(), 2);
         assert_eq!(
             results.into_iter().map(|(m, _d)| m.id).collect::<Vec<_>>(),
@@ -1065,7 +2519,7 @@ mod test {
         );
         state.mechs.get_mut("steve").unwrap().team = "boylur".to_string();
         let origin = Point::new(10, 6); // al's position
-        let results = radar::radar_ping(&state, &origin, "earth");
+        let results = radar::radar_ping(&state, &origin, "earth", &None);
         assert_eq!(
             results,
             vec![
@@ -1075,6 +2529,7 @@ mod test {
                     foe: false,
                     distance: 3,
                     location: Point::new(13, 9),
+                    kind: PingKind::Mech,
                 },
                 RadarPing {
                     name: "steve's Mech".to_string(),
@@ -1082,6 +2537,7 @@ mod test {
                     foe: true,
                     distance: 4,
                     location: Point::new(14, 6),
+                    kind: PingKind::Mech,
                 }
             ]
         )
@@ -1130,10 +2586,12 @@ mod test {
             MechCommand::RequestRadarScan {
                 mech: "al".to_string(),
                 turn: 0,
+                direction: None,
             },
             MechCommand::RequestRadarScan {
                 mech: "nobody".to_string(),
                 turn: 0,
+                direction: None,
             },
         ];
 
@@ -1153,6 +2611,7 @@ mod test {
                     foe: false,
                     distance: 3,
                     location: Point::new(13, 9),
+                    kind: PingKind::Mech,
                 },
                 RadarPing {
                     name: "steve's Mech".to_string(),
@@ -1160,6 +2619,7 @@ mod test {
                     foe: false,
                     distance: 4,
                     location: Point::new(14, 6),
+                    kind: PingKind::Mech,
                 }
             ]
         );
@@ -1172,6 +2632,7 @@ mod test {
                 foe: false,
                 distance: 3,
                 location: Point::new(13, 9),
+                kind: PingKind::Mech,
             },]
         )
     }
@@ -1228,6 +2689,35 @@ mod test {
 
         assert_eq!(Point::new(10, 8), state.mechs["bounce"].position); // Go north until we can't, then find adjacent
     }
+
+    #[test]
+    fn cannot_spawn_on_full_board() {
+        let mut params = gen_parameters(&[], 10);
+        params.width = 2;
+        params.height = 2;
+        // points on both edges of the board are on it
+        let spawns: Vec<(String, Point)> = (0..9)
+            .map(|i| (format!("mech{}", i), Point::new(i % 3, i / 3)))
+            .collect();
+        let state = gen_root_state_with_parameters(
+            spawns
+                .iter()
+                .map(|(m, p)| (m.as_str(), p.clone()))
+                .collect(),
+            params,
+        );
+        assert_eq!(state.mechs.len(), 9);
+
+        let cmd = MechCommand::SpawnMech {
+            position: Point::new(0, 0),
+            avatar: "".to_string(),
+            mech: "late".to_string(),
+            name: "test".to_string(),
+            team: "earth".to_string(),
+        };
+        let evts = Match::handle_command(&state, &cmd).unwrap();
+        assert!(matches!(&evts[..], [GameEvent::CommandRejected { .. }]));
+    }
     #[test]
     fn action_points_limit() {
         let state = gen_root_state(
@@ -1337,6 +2827,194 @@ mod test {
         );
     }
 
+    #[test]
+    fn expanded_register_file() {
+        let mechs = vec![("al", Point::new(10, 6)), ("bob", Point::new(16, 11))];
+        let params = MatchParameters {
+            ruleset: Ruleset {
+                registers: RegisterFile {
+                    numeric: 2,
+                    text: 1,
+                    memory_quota: 4,
+                },
+                ..Default::default()
+            },
+            ..gen_parameters(&mechs, 10)
+        };
+        let state = gen_root_state_with_parameters(mechs, params);
+        let set = |state: &MatchState, reg, val| {
+            Match::handle_command(
+                state,
+                &MechCommand::RegisterUpdate {
+                    turn: 0,
+                    mech: "al".to_string(),
+                    reg,
+                    op: RegisterOperation::Set(val),
+                },
+            )
+            .unwrap()
+        };
+
+        let state = apply_command(
+            state,
+            &MechCommand::RegisterUpdate {
+                turn: 0,
+                mech: "al".to_string(),
+                reg: numeric_register(1),
+                op: RegisterOperation::Set(RegisterValue::Number(7)),
+            },
+        );
+        let state = apply_command(
+            state,
+            &MechCommand::RegisterUpdate {
+                turn: 0,
+                mech: "al".to_string(),
+                reg: text_register(0),
+                op: RegisterOperation::Set(RegisterValue::Text("bob".to_string())),
+            },
+        );
+        let state = apply_command(
+            state,
+            &MechCommand::RegisterUpdate {
+                turn: 0,
+                mech: "al".to_string(),
+                reg: MEM,
+                op: RegisterOperation::Set(RegisterValue::Bytes(vec![1, 2, 3, 4])),
+            },
+        );
+        let registers = &state.mechs["al"].registers;
+        assert!(matches!(
+            registers.get(&numeric_register(1)),
+            Some(RegisterValue::Number(7))
+        ));
+        assert!(
+            matches!(registers.get(&text_register(0)), Some(RegisterValue::Text(t)) if t == "bob")
+        );
+        assert!(matches!(registers.get(&MEM), Some(RegisterValue::Bytes(b)) if b.len() == 4));
+
+        // registers beyond the file and writes beyond the memory quota are rejected
+        for evts in vec![
+            set(&state, numeric_register(2), RegisterValue::Number(1)),
+            set(
+                &state,
+                text_register(1),
+                RegisterValue::Text("x".to_string()),
+            ),
+            set(&state, MEM, RegisterValue::Bytes(vec![0; 5])),
+        ] {
+            assert!(matches!(&evts[..], [GameEvent::CommandRejected { .. }]));
+        }
+        assert!(matches!(
+            &set(&state, EBX, RegisterValue::Number(1))[..],
+            [GameEvent::RegisterOperationRejected {
+                fault: RegisterFault::WrongKind {
+                    kind: RegisterKind::Text
+                },
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn register_operations() {
+        let state = gen_register_state();
+        let op = |state: MatchState, reg, op| {
+            apply_command(
+                state,
+                &MechCommand::RegisterUpdate {
+                    turn: 0,
+                    mech: "al".to_string(),
+                    reg,
+                    op,
+                },
+            )
+        };
+        let number = |state: &MatchState, reg| match state.mechs["al"].registers.get(&reg) {
+            Some(RegisterValue::Number(n)) => Some(*n),
+            _ => None,
+        };
+
+        // arithmetic starts from 0 in an empty register
+        let state = op(state, EAX, RegisterOperation::Accumulate(6));
+        let state = op(state, EAX, RegisterOperation::Multiply(7));
+        assert_eq!(number(&state, EAX), Some(42));
+        let state = op(state, EAX, RegisterOperation::Modulo(10));
+        assert_eq!(number(&state, EAX), Some(2));
+
+        let state = op(
+            state,
+            EAX,
+            RegisterOperation::CompareAndSet {
+                expected: RegisterValue::Number(2),
+                new: RegisterValue::Number(9),
+            },
+        );
+        assert_eq!(number(&state, EAX), Some(9));
+
+        let state = op(state, ECX, RegisterOperation::Copy { from: EAX });
+        assert_eq!(number(&state, ECX), Some(9));
+        let state = op(state, EAX, RegisterOperation::Clear);
+        assert_eq!(number(&state, EAX), None);
+        let state = op(state, EAX, RegisterOperation::Swap { with: ECX });
+        assert_eq!(number(&state, EAX), Some(9));
+        assert_eq!(number(&state, ECX), None);
+    }
+
+    #[test]
+    fn register_operations_rejected() {
+        let state = gen_register_state();
+        let fault = |reg, op| match &Match::handle_command(
+            &state,
+            &MechCommand::RegisterUpdate {
+                turn: 0,
+                mech: "al".to_string(),
+                reg,
+                op,
+            },
+        )
+        .unwrap()[..]
+        {
+            [GameEvent::RegisterOperationRejected { fault, .. }] => Some(fault.clone()),
+            _ => None,
+        };
+
+        assert_eq!(
+            fault(EBX, RegisterOperation::Accumulate(1)),
+            Some(RegisterFault::WrongKind {
+                kind: RegisterKind::Text
+            })
+        );
+        assert_eq!(
+            fault(EAX, RegisterOperation::Modulo(0)),
+            Some(RegisterFault::DivisionByZero)
+        );
+        assert_eq!(
+            fault(
+                EAX,
+                RegisterOperation::CompareAndSet {
+                    expected: RegisterValue::Number(1),
+                    new: RegisterValue::Number(2),
+                }
+            ),
+            Some(RegisterFault::Mismatch)
+        );
+        assert_eq!(
+            fault(EAX, RegisterOperation::Copy { from: ECX }),
+            Some(RegisterFault::Empty { reg: ECX })
+        );
+        assert_eq!(
+            fault(EAX, RegisterOperation::Swap { with: MEM }),
+            Some(RegisterFault::WrongKind {
+                kind: RegisterKind::Bytes
+            })
+        );
+    }
+
+    fn gen_register_state() -> MatchState {
+        let mechs = vec![("al", Point::new(10, 6)), ("bob", Point::new(16, 11))];
+        gen_root_state_with_parameters(mechs.clone(), gen_parameters(&mechs, 10))
+    }
+
     #[test]
     fn register_acc_modifies_mech_state() {
         let mech1 = "johnny";
@@ -1672,4 +3350,1580 @@ mod test {
             panic!("Mech 1 EBX register was modified by an invalid operation");
         };
     }
+
+    fn gen_root_state_with_terrain(
+        mechs: Vec<(&str, Point)>,
+        terrain: Vec<Obstacle>,
+    ) -> MatchState {
+        let params = MatchParameters {
+            terrain,
+            ..gen_parameters(&mechs, 10)
+        };
+        gen_root_state_with_parameters(mechs, params)
+    }
+
+    #[test]
+    fn move_into_wall_causes_collision_dmg() {
+        let state = gen_root_state_with_terrain(
+            vec![("jeeves", Point::new(5, 5))],
+            vec![Obstacle::new(Point::new(6, 6), TerrainType::Wall)],
+        );
+        let cmd = MechCommand::Move {
+            turn: 0,
+            mech: "jeeves".to_string(),
+            direction: GridDirection::NorthEast,
+        };
+
+        let state = apply_command(state, &cmd);
+
+        assert_eq!(state.mechs["jeeves"].health, INITIAL_HEALTH - WALL_DAMAGE);
+        assert_eq!(state.mechs["jeeves"].position, Point::new(5, 5));
+    }
+
+    #[test]
+    fn move_into_pit_causes_pit_dmg() {
+        let state = gen_root_state_with_terrain(
+            vec![("jeeves", Point::new(5, 5))],
+            vec![Obstacle::new(Point::new(5, 6), TerrainType::Pit)],
+        );
+        let cmd = MechCommand::Move {
+            turn: 0,
+            mech: "jeeves".to_string(),
+            direction: GridDirection::North,
+        };
+
+        let state = apply_command(state, &cmd);
+
+        assert_eq!(state.mechs["jeeves"].health, INITIAL_HEALTH - PIT_DAMAGE);
+        assert_eq!(state.mechs["jeeves"].position, Point::new(5, 5));
+    }
+
+    #[test]
+    fn pit_can_destroy_mech() {
+        let mut state = gen_root_state_with_terrain(
+            vec![("jeeves", Point::new(5, 5)), ("wooster", Point::new(8, 8))],
+            vec![Obstacle::new(Point::new(5, 6), TerrainType::Pit)],
+        );
+        state.mechs.get_mut("jeeves").unwrap().health = PIT_DAMAGE;
+        let cmd = MechCommand::Move {
+            turn: 0,
+            mech: "jeeves".to_string(),
+            direction: GridDirection::North,
+        };
+
+        let state = apply_command(state, &cmd);
+
+        assert!(!state.mechs["jeeves"].alive);
+        assert_eq!(
+            state.completed,
+            Some(EndCause::MechVictory("wooster".to_string()))
+        );
+    }
+
+    #[test]
+    fn wall_blocks_primary_fire() {
+        let state = gen_root_state_with_terrain(
+            vec![
+                ("shooter", Point::new(10, 6)),
+                ("victim", Point::new(12, 8)),
+            ],
+            vec![Obstacle::new(Point::new(11, 7), TerrainType::Wall)],
+        );
+        let cmd = MechCommand::FirePrimary {
+            turn: 0,
+            mech: "shooter".to_string(),
+            direction: GridDirection::NorthEast,
+        };
+
+        let state = apply_command(state, &cmd);
+
+        assert_eq!(state.mechs["victim"].health, INITIAL_HEALTH);
+        assert!(state.game_board.is_blocked(&Point::new(11, 7)));
+    }
+
+    #[test]
+    fn cover_destroyed_by_fire() {
+        let state = gen_root_state_with_terrain(
+            vec![
+                ("shooter", Point::new(10, 6)),
+                ("victim", Point::new(12, 8)),
+            ],
+            vec![Obstacle::new(
+                Point::new(11, 7),
+                TerrainType::Cover { health: 150 },
+            )],
+        );
+        let cmd = MechCommand::FirePrimary {
+            turn: 0,
+            mech: "shooter".to_string(),
+            direction: GridDirection::NorthEast,
+        };
+
+        let state = apply_command(state, &cmd);
+        assert_eq!(
+            state
+                .game_board
+                .obstacle_at(&Point::new(11, 7))
+                .unwrap()
+                .terrain,
+            TerrainType::Cover {
+                health: 150 - PRIMARY_DAMAGE
+            }
+        );
+
+        let state = apply_command(state, &cmd);
+        assert!(state.game_board.obstacle_at(&Point::new(11, 7)).is_none());
+        assert_eq!(state.mechs["victim"].health, INITIAL_HEALTH);
+    }
+
+    #[test]
+    fn secondary_fire_splashes_at_obstacle() {
+        let state = gen_root_state_with_terrain(
+            vec![
+                ("shooter", Point::new(10, 6)),
+                ("victim", Point::new(14, 10)),
+            ],
+            vec![Obstacle::new(Point::new(13, 9), TerrainType::Wall)],
+        );
+        let cmd = MechCommand::FireSecondary {
+            turn: 0,
+            mech: "shooter".to_string(),
+            direction: GridDirection::NorthEast,
+        };
+
+        let state = apply_command(state, &cmd);
+
+        assert_eq!(
+            state.mechs["victim"].health,
+            INITIAL_HEALTH - SECONDARY_SPLASH_DAMAGE
+        );
+    }
+
+    #[test]
+    fn radar_reports_obstacles_and_stops_at_walls() {
+        let state = gen_root_state_with_terrain(
+            vec![
+                ("al", Point::new(10, 6)),
+                ("bob", Point::new(13, 9)),
+                ("steve", Point::new(14, 6)),
+            ],
+            vec![
+                Obstacle::new(Point::new(12, 6), TerrainType::Wall),
+                Obstacle::new(Point::new(11, 7), TerrainType::Pit),
+            ],
+        );
+        let results = radar::radar_ping(&state, &Point::new(10, 6), "earth", &None);
+        assert_eq!(
+            results,
+            vec![
+                RadarPing {
+                    name: "obstacle-11-7".to_string(),
+                    avatar: "none".to_string(),
+                    foe: false,
+                    distance: 1,
+                    location: Point::new(11, 7),
+                    kind: PingKind::Obstacle(TerrainType::Pit),
+                },
+                RadarPing {
+                    name: "bob's Mech".to_string(),
+                    avatar: "none".to_string(),
+                    foe: false,
+                    distance: 3,
+                    location: Point::new(13, 9),
+                    kind: PingKind::Mech,
+                },
+                RadarPing {
+                    name: "obstacle-12-6".to_string(),
+                    avatar: "none".to_string(),
+                    foe: false,
+                    distance: 2,
+                    location: Point::new(12, 6),
+                    kind: PingKind::Obstacle(TerrainType::Wall),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn cannot_spawn_on_obstacle() {
+        let state = gen_root_state_with_terrain(
+            vec![("al", Point::new(10, 6))],
+            vec![Obstacle::new(Point::new(10, 6), TerrainType::Wall)],
+        );
+
+        assert_eq!(Point::new(10, 7), state.mechs["al"].position);
+    }
+
+    #[test]
+    fn radar_star_occluded_by_mech() {
+        let state = gen_root_state(
+            vec![
+                ("al", Point::new(10, 6)),
+                ("bob", Point::new(12, 6)),
+                ("steve", Point::new(14, 6)),
+            ],
+            10,
+        );
+        let results = radar::collect_targets(&state, &Point::new(10, 6), &None);
+        assert_eq!(
+            results.into_iter().map(|(m, _d)| m.id).collect::<Vec<_>>(),
+            vec!["bob"]
+        );
+    }
+
+    #[test]
+    fn radar_circle_sees_off_axis() {
+        let mechs = vec![
+            ("al", Point::new(10, 6)),
+            ("bob", Point::new(12, 7)),
+            ("steve", Point::new(10, 12)),
+        ];
+        let params = MatchParameters {
+            ruleset: Ruleset {
+                radar_pattern: RadarPattern::Circle { radius: 5 },
+                ..Default::default()
+            },
+            ..gen_parameters(&mechs, 10)
+        };
+        let state = gen_root_state_with_parameters(mechs, params);
+
+        let results = radar::collect_targets(&state, &Point::new(10, 6), &None);
+        assert_eq!(
+            results
+                .into_iter()
+                .map(|(m, d)| (m.id, d))
+                .collect::<Vec<_>>(),
+            vec![("bob".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn radar_circle_occluded_by_wall() {
+        let mechs = vec![("al", Point::new(10, 6)), ("bob", Point::new(14, 8))];
+        let params = MatchParameters {
+            ruleset: Ruleset {
+                radar_pattern: RadarPattern::Circle { radius: 5 },
+                ..Default::default()
+            },
+            terrain: vec![Obstacle::new(Point::new(12, 7), TerrainType::Wall)],
+            ..gen_parameters(&mechs, 10)
+        };
+        let state = gen_root_state_with_parameters(mechs, params);
+
+        assert_eq!(
+            radar::radar_ping(&state, &Point::new(10, 6), "earth", &None),
+            vec![RadarPing {
+                name: "obstacle-12-7".to_string(),
+                avatar: "none".to_string(),
+                foe: false,
+                distance: 2,
+                location: Point::new(12, 7),
+                kind: PingKind::Obstacle(TerrainType::Wall),
+            }]
+        );
+    }
+
+    #[test]
+    fn radar_cone_only_sees_ahead() {
+        let mechs = vec![
+            ("al", Point::new(10, 6)),
+            ("bob", Point::new(10, 9)),
+            ("steve", Point::new(10, 3)),
+            ("nobody", Point::new(13, 6)),
+        ];
+        let params = MatchParameters {
+            ruleset: Ruleset {
+                radar_pattern: RadarPattern::Cone { radius: 6, arc: 90 },
+                ..Default::default()
+            },
+            ..gen_parameters(&mechs, 10)
+        };
+        let state = gen_root_state_with_parameters(mechs, params);
+
+        let north = radar::collect_targets(&state, &Point::new(10, 6), &None);
+        assert_eq!(
+            north.into_iter().map(|(m, _d)| m.id).collect::<Vec<_>>(),
+            vec!["bob"]
+        );
+        let south = radar::collect_targets(&state, &Point::new(10, 6), &Some(GridDirection::South));
+        assert_eq!(
+            south.into_iter().map(|(m, _d)| m.id).collect::<Vec<_>>(),
+            vec!["steve"]
+        );
+    }
+
+    #[test]
+    fn simultaneous_moves_into_same_space_collide() {
+        let mechs = vec![("al", Point::new(5, 5)), ("bob", Point::new(7, 5))];
+        let state = gen_root_state(mechs, 10);
+        let orders = vec![
+            (
+                "al".to_string(),
+                vec![MechCommand::Move {
+                    turn: 0,
+                    mech: "al".to_string(),
+                    direction: GridDirection::East,
+                }],
+            ),
+            (
+                "bob".to_string(),
+                vec![MechCommand::Move {
+                    turn: 0,
+                    mech: "bob".to_string(),
+                    direction: GridDirection::West,
+                }],
+            ),
+        ];
+        let evts = Match::resolve_simultaneous(&state, &orders).unwrap();
+        let state = evts.iter().fold(state, |state, (_, evt)| {
+            Match::apply_event(&state, evt).unwrap()
+        });
+
+        assert_eq!(state.mechs["al"].position, Point::new(5, 5));
+        assert_eq!(state.mechs["bob"].position, Point::new(7, 5));
+        assert_eq!(state.mechs["al"].health, INITIAL_HEALTH - WALL_DAMAGE);
+        assert_eq!(state.mechs["bob"].health, INITIAL_HEALTH - WALL_DAMAGE);
+    }
+
+    #[test]
+    fn simultaneous_move_to_same_space_collide() {
+        let mechs = vec![("al", Point::new(5, 5)), ("bob", Point::new(9, 5))];
+        let state = gen_root_state(mechs, 10);
+        let move_to = |mech: &str| {
+            (
+                mech.to_string(),
+                vec![MechCommand::MoveTo {
+                    turn: 0,
+                    mech: mech.to_string(),
+                    target: Point::new(7, 5),
+                }],
+            )
+        };
+        let orders = vec![move_to("al"), move_to("bob")];
+        let evts = Match::resolve_simultaneous(&state, &orders).unwrap();
+        let state = evts.iter().fold(state, |state, (_, evt)| {
+            Match::apply_event(&state, evt).unwrap()
+        });
+
+        // both take their first step, then collide heading into the same space
+        assert_eq!(state.mechs["al"].position, Point::new(6, 5));
+        assert_eq!(state.mechs["bob"].position, Point::new(8, 5));
+        assert_eq!(state.mechs["al"].health, INITIAL_HEALTH - WALL_DAMAGE);
+        assert_eq!(state.mechs["bob"].health, INITIAL_HEALTH - WALL_DAMAGE);
+        assert_eq!(state.mechs["al"].remaining_aps, APS_PER_TURN - 2);
+    }
+
+    #[test]
+    fn simultaneous_move_to_stops_short_of_arriving_mech() {
+        let mechs = vec![("al", Point::new(5, 5)), ("bob", Point::new(7, 6))];
+        let state = gen_root_state(mechs, 10);
+        let orders = vec![
+            (
+                "al".to_string(),
+                vec![MechCommand::MoveTo {
+                    turn: 0,
+                    mech: "al".to_string(),
+                    target: Point::new(7, 5),
+                }],
+            ),
+            (
+                "bob".to_string(),
+                vec![MechCommand::Move {
+                    turn: 0,
+                    mech: "bob".to_string(),
+                    direction: GridDirection::South,
+                }],
+            ),
+        ];
+        let evts = Match::resolve_simultaneous(&state, &orders).unwrap();
+        let state = evts.iter().fold(state, |state, (_, evt)| {
+            Match::apply_event(&state, evt).unwrap()
+        });
+
+        assert_eq!(state.mechs["bob"].position, Point::new(7, 5));
+        assert_eq!(state.mechs["al"].position, Point::new(6, 5));
+        assert_eq!(state.mechs["al"].health, INITIAL_HEALTH - WALL_DAMAGE);
+    }
+
+    #[test]
+    fn simultaneous_rejection_spares_other_orders() {
+        let mechs = vec![("al", Point::new(5, 5)), ("bob", Point::new(5, 7))];
+        let state = gen_root_state(mechs, 10);
+        let finish = |mech: &str| MechCommand::FinishTurn {
+            mech: mech.to_string(),
+            turn: 0,
+        };
+        let orders = vec![
+            (
+                "al".to_string(),
+                vec![
+                    MechCommand::Move {
+                        turn: 0,
+                        mech: "al".to_string(),
+                        direction: GridDirection::East,
+                    },
+                    finish("al"),
+                ],
+            ),
+            // not a mech in this match
+            ("carl".to_string(), vec![finish("carl")]),
+            ("bob".to_string(), vec![finish("bob")]),
+        ];
+        let evts = Match::resolve_simultaneous(&state, &orders).unwrap();
+        assert!(evts.iter().any(
+            |(actor, evt)| actor == "carl" && matches!(evt, GameEvent::CommandRejected { .. })
+        ));
+        let state = evts.iter().fold(state, |state, (_, evt)| {
+            Match::apply_event(&state, evt).unwrap()
+        });
+
+        assert_eq!(state.mechs["al"].position, Point::new(6, 5));
+        assert_eq!(state.turn_status.current, 1);
+    }
+
+    #[test]
+    fn simultaneous_fire_resolves_after_moves() {
+        let mechs = vec![("al", Point::new(5, 5)), ("bob", Point::new(5, 7))];
+        let state = gen_root_state(mechs, 10);
+        let orders = vec![
+            (
+                "bob".to_string(),
+                vec![MechCommand::FirePrimary {
+                    turn: 0,
+                    mech: "bob".to_string(),
+                    direction: GridDirection::South,
+                }],
+            ),
+            (
+                "al".to_string(),
+                vec![MechCommand::Move {
+                    turn: 0,
+                    mech: "al".to_string(),
+                    direction: GridDirection::East,
+                }],
+            ),
+        ];
+        let evts = Match::resolve_simultaneous(&state, &orders).unwrap();
+        let state = evts.iter().fold(state, |state, (_, evt)| {
+            Match::apply_event(&state, evt).unwrap()
+        });
+
+        assert_eq!(state.mechs["al"].position, Point::new(6, 5));
+        assert_eq!(state.mechs["al"].health, INITIAL_HEALTH);
+    }
+
+    #[test]
+    fn simultaneous_mutual_kill_is_draw() {
+        let mechs = vec![("al", Point::new(5, 5)), ("bob", Point::new(5, 7))];
+        let params = MatchParameters {
+            ruleset: Ruleset {
+                initial_health: 100,
+                ..Default::default()
+            },
+            ..gen_parameters(&mechs, 10)
+        };
+        let state = gen_root_state_with_parameters(mechs, params);
+        let fire = |mech: &str, direction| {
+            (
+                mech.to_string(),
+                vec![
+                    MechCommand::FirePrimary {
+                        turn: 0,
+                        mech: mech.to_string(),
+                        direction,
+                    },
+                    MechCommand::FinishTurn {
+                        mech: mech.to_string(),
+                        turn: 0,
+                    },
+                ],
+            )
+        };
+        let orders = vec![
+            fire("al", GridDirection::North),
+            fire("bob", GridDirection::South),
+        ];
+        let evts = Match::resolve_simultaneous(&state, &orders).unwrap();
+        let state = evts.iter().fold(state, |state, (_, evt)| {
+            Match::apply_event(&state, evt).unwrap()
+        });
+
+        assert!(!state.mechs["al"].alive);
+        assert!(!state.mechs["bob"].alive);
+        assert_eq!(
+            state.completed,
+            Some(EndCause::Draw {
+                mechs: vec!["al".to_string(), "bob".to_string()]
+            })
+        );
+        assert_eq!(state.turn_status.current, 1);
+    }
+
+    fn turn_order_for(state: &MatchState, turn: u32) -> Vec<String> {
+        let evts = Match::begin_turn(state, turn);
+        let state = Match::apply_event(state, &evts[0]).unwrap();
+        state.turn_status.order
+    }
+
+    #[test]
+    fn round_robin_turn_order_rotates() {
+        let mechs = vec![
+            ("al", Point::new(1, 1)),
+            ("bob", Point::new(5, 5)),
+            ("steve", Point::new(9, 9)),
+        ];
+        let params = MatchParameters {
+            turn_order: TurnOrder::RoundRobin,
+            ..gen_parameters(&mechs, 10)
+        };
+        let state = gen_root_state_with_parameters(mechs, params);
+
+        assert_eq!(turn_order_for(&state, 0), vec!["al", "bob", "steve"]);
+        assert_eq!(turn_order_for(&state, 1), vec!["bob", "steve", "al"]);
+        assert_eq!(turn_order_for(&state, 2), vec!["steve", "al", "bob"]);
+        assert_eq!(turn_order_for(&state, 3), vec!["al", "bob", "steve"]);
+    }
+
+    #[test]
+    fn initiative_turn_order_favors_damaged_mechs() {
+        let mechs = vec![
+            ("al", Point::new(1, 1)),
+            ("bob", Point::new(5, 5)),
+            ("steve", Point::new(9, 9)),
+        ];
+        let params = MatchParameters {
+            turn_order: TurnOrder::Initiative,
+            ..gen_parameters(&mechs, 10)
+        };
+        let state = gen_root_state_with_parameters(mechs, params);
+        let state = Match::apply_event(
+            &state,
+            &GameEvent::DamageTaken {
+                damage_target: "steve".to_string(),
+                damage: 100,
+                damage_source: DamageSource::Wall,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(turn_order_for(&state, 0), vec!["steve", "al", "bob"]);
+    }
+
+    #[test]
+    fn random_turn_order_is_reproducible() {
+        let mechs = vec![
+            ("al", Point::new(1, 1)),
+            ("bob", Point::new(5, 5)),
+            ("steve", Point::new(9, 9)),
+            ("wanda", Point::new(13, 13)),
+        ];
+        let params = MatchParameters {
+            turn_order: TurnOrder::Random,
+            seed: 42,
+            ..gen_parameters(&mechs, 10)
+        };
+        let state = gen_root_state_with_parameters(mechs, params);
+
+        let orders: Vec<_> = (0..10).map(|turn| turn_order_for(&state, turn)).collect();
+        let again: Vec<_> = (0..10).map(|turn| turn_order_for(&state, turn)).collect();
+        assert_eq!(orders, again);
+        assert!(orders.iter().any(|o| o != &orders[0]));
+        for order in orders {
+            let mut sorted = order.clone();
+            sorted.sort();
+            assert_eq!(sorted, vec!["al", "bob", "steve", "wanda"]);
+        }
+    }
+
+    fn overspend(state: MatchState, mech: &str, shots: u32) -> MatchState {
+        (0..shots).fold(state, |state, _| {
+            let cmd = MechCommand::FireSecondary {
+                turn: 0,
+                mech: mech.to_string(),
+                direction: GridDirection::South,
+            };
+            apply_command(state, &cmd)
+        })
+    }
+
+    #[test]
+    fn ap_violation_hull_damage_penalty() {
+        let state = gen_ruleset_state(
+            vec![("al", Point::new(10, 6)), ("bob", Point::new(16, 11))],
+            Ruleset {
+                ap_penalty: ApPenaltyPolicy::HullDamage { damage: 75 },
+                ..Default::default()
+            },
+        );
+        let state = overspend(state, "al", 3);
+
+        assert_eq!(state.mechs["al"].ap_violations, 2);
+        assert_eq!(state.mechs["al"].health, INITIAL_HEALTH - 150);
+        assert_eq!(
+            state.mechs["al"].penalties,
+            vec![(0, Penalty::HullDamage(75)), (0, Penalty::HullDamage(75))]
+        );
+    }
+
+    #[test]
+    fn ap_violation_forfeits_next_turn() {
+        let state = gen_ruleset_state(
+            vec![("al", Point::new(10, 6)), ("bob", Point::new(16, 11))],
+            Ruleset {
+                ap_penalty: ApPenaltyPolicy::ForfeitNextTurn,
+                ..Default::default()
+            },
+        );
+        let state = overspend(state, "al", 2);
+        let state =
+            Match::apply_event(&state, &GameEvent::MatchTurnCompleted { new_turn: 1 }).unwrap();
+
+        assert_eq!(state.mechs["al"].remaining_aps, 0);
+        assert_eq!(state.mechs["bob"].remaining_aps, 4);
+        assert_eq!(
+            state.mechs["al"].penalties,
+            vec![(0, Penalty::ActionPointsForfeited)]
+        );
+
+        let state =
+            Match::apply_event(&state, &GameEvent::MatchTurnCompleted { new_turn: 2 }).unwrap();
+        assert_eq!(state.mechs["al"].remaining_aps, 4);
+        assert!(state.mechs["al"].penalties.is_empty());
+    }
+
+    #[test]
+    fn ap_violations_lead_to_disqualification() {
+        let state = gen_ruleset_state(
+            vec![("al", Point::new(10, 6)), ("bob", Point::new(16, 11))],
+            Ruleset {
+                ap_penalty: ApPenaltyPolicy::Disqualify { after: 2 },
+                ..Default::default()
+            },
+        );
+        let state = overspend(state, "al", 2);
+        assert!(state.mechs["al"].alive);
+        assert!(state.completed.is_none());
+
+        let state = overspend(state, "al", 1);
+        assert!(!state.mechs["al"].alive);
+        assert_eq!(
+            state.completed,
+            Some(EndCause::Forfeit {
+                victor: "bob".to_string(),
+                forfeited: vec!["al".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn simultaneous_disqualification_destroys_once() {
+        let mut state = gen_ruleset_state(
+            vec![("al", Point::new(10, 6)), ("bob", Point::new(16, 11))],
+            Ruleset {
+                ap_penalty: ApPenaltyPolicy::Disqualify { after: 1 },
+                ..Default::default()
+            },
+        );
+        state.mechs.get_mut("al").unwrap().remaining_aps = 0;
+        let orders = vec![(
+            "al".to_string(),
+            vec![MechCommand::Move {
+                turn: 0,
+                mech: "al".to_string(),
+                direction: GridDirection::North,
+            }],
+        )];
+        let evts = Match::resolve_simultaneous(&state, &orders).unwrap();
+        let count = |f: fn(&GameEvent) -> bool| evts.iter().filter(|(_, evt)| f(evt)).count();
+        assert_eq!(
+            count(|evt| matches!(evt, GameEvent::MechDestroyed { .. })),
+            1
+        );
+        assert_eq!(
+            count(|evt| matches!(evt, GameEvent::GameFinished { .. })),
+            1
+        );
+        let state = evts.iter().fold(state, |state, (_, evt)| {
+            Match::apply_event(&state, evt).unwrap()
+        });
+        assert_eq!(
+            state.completed,
+            Some(EndCause::Forfeit {
+                victor: "bob".to_string(),
+                forfeited: vec!["al".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn repeated_timeouts_lead_to_ejection() {
+        let state = gen_ruleset_state(
+            vec![("al", Point::new(10, 6)), ("bob", Point::new(16, 11))],
+            Ruleset {
+                max_timeouts: 3,
+                ..Default::default()
+            },
+        );
+        let time_out = |state: MatchState| {
+            Match::time_out(&state, "al")
+                .iter()
+                .fold(state, |state, evt| Match::apply_event(&state, evt).unwrap())
+        };
+
+        let state = time_out(state);
+        assert_eq!(state.mechs["al"].timeouts, 1);
+        assert_eq!(state.mechs["al"].remaining_aps, 0);
+        let state = time_out(time_out(state));
+        assert!(!state.mechs["al"].alive);
+        assert_eq!(state.mechs["al"].penalties, vec![(0, Penalty::Ejected)]);
+        assert_eq!(
+            state.completed,
+            Some(EndCause::Forfeit {
+                victor: "bob".to_string(),
+                forfeited: vec!["al".to_string()],
+            })
+        );
+        assert!(Match::time_out(&state, "al").is_empty());
+    }
+
+    fn gen_team_state(friendly_fire: FriendlyFire) -> MatchState {
+        // al and bob are teammates standing in a line, with steve at the end
+        let mechs = vec![
+            ("al", Point::new(5, 5)),
+            ("bob", Point::new(5, 6)),
+            ("steve", Point::new(5, 7)),
+        ];
+        let params = MatchParameters {
+            ruleset: Ruleset {
+                friendly_fire,
+                ..Default::default()
+            },
+            teams: vec![("al", "red"), ("bob", "red"), ("steve", "blue")]
+                .into_iter()
+                .map(|(m, t)| (m.to_string(), t.to_string()))
+                .collect(),
+            ..gen_parameters(&mechs, 10)
+        };
+        gen_root_state_with_parameters(mechs, params)
+    }
+
+    fn fire_primary(state: MatchState, mech: &str, direction: GridDirection) -> MatchState {
+        let cmd = MechCommand::FirePrimary {
+            turn: 0,
+            mech: mech.to_string(),
+            direction,
+        };
+        apply_command(state, &cmd)
+    }
+
+    #[test]
+    fn friendly_fire_rules() {
+        let state = gen_team_state(FriendlyFire::Off);
+        assert_eq!(state.mechs["bob"].team, "red");
+        let state = fire_primary(state, "al", GridDirection::North);
+        assert_eq!(state.mechs["bob"].health, INITIAL_HEALTH);
+
+        let state = gen_team_state(FriendlyFire::Reduced { percent: 50 });
+        let state = fire_primary(state, "al", GridDirection::North);
+        assert_eq!(
+            state.mechs["bob"].health,
+            INITIAL_HEALTH - PRIMARY_DAMAGE / 2
+        );
+
+        let state = gen_team_state(FriendlyFire::Full);
+        let state = fire_primary(state, "steve", GridDirection::South);
+        assert_eq!(state.mechs["bob"].health, INITIAL_HEALTH - PRIMARY_DAMAGE);
+    }
+
+    #[test]
+    fn teammate_kills_are_friendly_fire() {
+        let state = gen_team_state(FriendlyFire::Full);
+        let killed_by = |attacker: &str, target: &str| {
+            Match::do_damage(
+                &state,
+                DamageSource::MechWeapon(attacker.to_string()),
+                target,
+                PRIMARY_DAMAGE,
+                PRIMARY_DAMAGE,
+            )
+            .into_iter()
+            .find_map(|evt| match evt {
+                GameEvent::MechDestroyed { friendly_fire, .. } => Some(friendly_fire),
+                _ => None,
+            })
+        };
+        assert_eq!(killed_by("al", "bob"), Some(true));
+        assert_eq!(killed_by("steve", "bob"), Some(false));
+    }
+
+    #[test]
+    fn large_damage_does_not_overflow() {
+        let state = gen_team_state(FriendlyFire::Reduced { percent: 50 });
+        let evts = Match::do_damage(
+            &state,
+            DamageSource::MechWeapon("al".to_string()),
+            "bob",
+            u32::MAX,
+            0,
+        );
+        assert!(evts.iter().any(|evt| matches!(
+            evt,
+            GameEvent::DamageTaken { damage, .. } if *damage == u32::MAX / 2
+        )));
+
+        let mut state = gen_team_state(FriendlyFire::Full);
+        state.mechs.get_mut("bob").unwrap().shield_raised = Some(0);
+        let evts = Match::do_damage(&state, DamageSource::Wall, "bob", u32::MAX, 0);
+        let blocked = u64::from(u32::MAX)
+            * u64::from(state.parameters.ruleset.shield_absorption.min(100))
+            / 100;
+        assert!(evts.iter().any(|evt| matches!(
+            evt,
+            GameEvent::DamageBlocked { damage, .. } if u64::from(*damage) == blocked
+        )));
+    }
+
+    #[test]
+    fn team_match_at_turn_limit_is_team_draw() {
+        let state = gen_team_state(FriendlyFire::Full);
+        let max_turns = state.parameters.max_turns;
+        let cmds: Vec<_> = (0..max_turns)
+            .flat_map(|turn| {
+                vec!["al", "bob", "steve"]
+                    .into_iter()
+                    .map(move |mech| MechCommand::FinishTurn {
+                        mech: mech.to_string(),
+                        turn,
+                    })
+            })
+            .collect();
+        let state = cmds.iter().fold(state, apply_command);
+        assert_eq!(
+            state.completed,
+            Some(EndCause::TeamDraw {
+                teams: vec!["blue".to_string(), "red".to_string()],
+                survivors: vec!["al".to_string(), "bob".to_string(), "steve".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn game_finishes_on_team_victory() {
+        let state = gen_team_state(FriendlyFire::Full);
+        let state = Match::apply_event(
+            &state,
+            &GameEvent::DamageTaken {
+                damage_target: "steve".to_string(),
+                damage: INITIAL_HEALTH - PRIMARY_DAMAGE,
+                damage_source: DamageSource::Wall,
+            },
+        )
+        .unwrap();
+        // bob's shot leaves the red team as the only one standing
+        let state = fire_primary(state, "bob", GridDirection::North);
+
+        assert!(!state.mechs["steve"].alive);
+        assert_eq!(
+            state.completed,
+            Some(EndCause::TeamVictory {
+                team: "red".to_string(),
+                survivors: vec!["al".to_string(), "bob".to_string()],
+                losers: vec!["steve".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn splash_destroying_last_mechs_is_draw() {
+        let mechs = vec![("al", Point::new(10, 6)), ("bob", Point::new(11, 6))];
+        let params = MatchParameters {
+            ruleset: Ruleset {
+                initial_health: SECONDARY_SPLASH_DAMAGE,
+                ..Default::default()
+            },
+            ..gen_parameters(&mechs, 10)
+        };
+        let state = gen_root_state_with_parameters(mechs, params);
+        let cmd = MechCommand::FireSecondary {
+            turn: 0,
+            mech: "al".to_string(),
+            direction: GridDirection::East,
+        };
+        let state = apply_command(state, &cmd);
+
+        assert!(!state.mechs["al"].alive);
+        assert!(!state.mechs["bob"].alive);
+        assert_eq!(
+            state.completed,
+            Some(EndCause::Draw {
+                mechs: vec!["al".to_string(), "bob".to_string()]
+            })
+        );
+    }
+
+    #[test]
+    fn disconnections_end_match() {
+        let state = gen_root_state(
+            vec![
+                ("al", Point::new(1, 1)),
+                ("bob", Point::new(5, 5)),
+                ("steve", Point::new(9, 9)),
+            ],
+            10,
+        );
+        let connected = |mechs: &[&str]| mechs.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+
+        assert!(Match::check_connections(&state, &connected(&["al", "bob", "steve"])).is_empty());
+        assert!(Match::check_connections(&state, &connected(&["al", "bob"])).is_empty());
+        match &Match::check_connections(&state, &connected(&["al"]))[..] {
+            [GameEvent::GameFinished { cause }] => assert_eq!(
+                *cause,
+                EndCause::Forfeit {
+                    victor: "al".to_string(),
+                    forfeited: connected(&["bob", "steve"]),
+                }
+            ),
+            evts => panic!("unexpected events {:?}", evts),
+        }
+        match &Match::check_connections(&state, &[])[..] {
+            [GameEvent::GameFinished { cause }] => {
+                assert_eq!(*cause, EndCause::AllMechsDisconnected)
+            }
+            evts => panic!("unexpected events {:?}", evts),
+        }
+    }
+
+    #[test]
+    fn aborted_match_only_ends_once() {
+        let state = gen_root_state(vec![("al", Point::new(1, 1))], 10);
+        let cause = EndCause::Aborted {
+            reason: "testing".to_string(),
+        };
+        let state = Match::end_match(&state, cause.clone())
+            .iter()
+            .fold(state, |state, evt| Match::apply_event(&state, evt).unwrap());
+
+        assert_eq!(state.completed, Some(cause.clone()));
+        assert!(Match::end_match(&state, cause).is_empty());
+    }
+
+    fn place_pickup(state: MatchState, position: Point, kind: PickupKind) -> MatchState {
+        let evt = GameEvent::PickupSpawned {
+            pickup: Pickup::new(position, kind),
+        };
+        Match::apply_event(&state, &evt).unwrap()
+    }
+
+    fn move_mech(state: MatchState, mech: &str, direction: GridDirection) -> MatchState {
+        let cmd = MechCommand::Move {
+            turn: 0,
+            mech: mech.to_string(),
+            direction,
+        };
+        apply_command(state, &cmd)
+    }
+
+    #[test]
+    fn moving_onto_pickup_collects_it() {
+        let state = gen_ruleset_state(
+            vec![("al", Point::new(5, 5))],
+            Ruleset {
+                secondary_ammo: Some(1),
+                ..Default::default()
+            },
+        );
+        let state = MatchState::modify_mech(&state, "al", |m| MechState { health: 900, ..m });
+        let state = place_pickup(
+            state,
+            Point::new(6, 5),
+            PickupKind::RepairKit { health: 200 },
+        );
+        let state = place_pickup(
+            state,
+            Point::new(7, 5),
+            PickupKind::ActionPointBoost { points: 2 },
+        );
+        let state = place_pickup(state, Point::new(8, 5), PickupKind::Ammo { rounds: 2 });
+
+        let state = move_mech(state, "al", GridDirection::East);
+        assert_eq!(state.mechs["al"].health, INITIAL_HEALTH); // capped at initial health
+        let state = move_mech(state, "al", GridDirection::East);
+        assert_eq!(state.mechs["al"].remaining_aps, 4);
+        let state = move_mech(state, "al", GridDirection::East);
+        assert_eq!(state.mechs["al"].ammo[&WeaponType::Secondary], 3);
+        assert!(!state.mechs["al"].ammo.contains_key(&WeaponType::Primary));
+        assert!(state.pickups.is_empty());
+    }
+
+    #[test]
+    fn pickups_spawn_on_schedule() {
+        let mechs = vec![("al", Point::new(5, 5)), ("bob", Point::new(10, 10))];
+        let params = MatchParameters {
+            ruleset: Ruleset {
+                pickups: PickupRules {
+                    interval: 2,
+                    limit: 1,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            seed: 42,
+            ..gen_parameters(&mechs, 10)
+        };
+        let state = gen_root_state_with_parameters(mechs, params);
+
+        assert!(Match::spawn_pickups(&state, 1).is_empty());
+        let evts = Match::spawn_pickups(&state, 2);
+        assert_eq!(evts.len(), 1);
+        let state = evts
+            .iter()
+            .fold(state, |state, evt| Match::apply_event(&state, evt).unwrap());
+        let pickup = &state.pickups[0];
+        assert!(MatchState::mech_at(&state, &pickup.position).is_none());
+
+        // the board already holds as many pickups as the rules allow
+        assert!(Match::spawn_pickups(&state, 4).is_empty());
+    }
+
+    #[test]
+    fn huge_pickups_saturate() {
+        let state = gen_ruleset_state(
+            vec![("al", Point::new(5, 5))],
+            Ruleset {
+                secondary_ammo: Some(1),
+                ..Default::default()
+            },
+        );
+        let state = place_pickup(
+            state,
+            Point::new(6, 5),
+            PickupKind::ActionPointBoost { points: u32::MAX },
+        );
+        let state = place_pickup(
+            state,
+            Point::new(7, 5),
+            PickupKind::Ammo { rounds: u32::MAX },
+        );
+        let state = place_pickup(
+            state,
+            Point::new(8, 5),
+            PickupKind::RepairKit { health: u32::MAX },
+        );
+
+        let state = move_mech(state, "al", GridDirection::East);
+        // the step onto the pickup is paid for once the boost has been collected
+        assert_eq!(state.mechs["al"].remaining_aps, u32::MAX - MOVE_COST);
+        let state = move_mech(state, "al", GridDirection::East);
+        assert_eq!(state.mechs["al"].ammo[&WeaponType::Secondary], u32::MAX);
+        let state = move_mech(state, "al", GridDirection::East);
+        assert_eq!(state.mechs["al"].health, INITIAL_HEALTH);
+    }
+
+    #[test]
+    fn pickups_never_spawn_on_empty_board() {
+        let params = MatchParameters {
+            width: 0,
+            height: 0,
+            ruleset: Ruleset {
+                pickups: PickupRules {
+                    interval: 1,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let state = MatchState::new_with_parameters(params);
+        assert!(Match::spawn_pickups(&state, 1).is_empty());
+    }
+
+    #[test]
+    fn radar_pings_pickups() {
+        let state = gen_root_state(vec![("al", Point::new(5, 5))], 10);
+        let state = place_pickup(state, Point::new(5, 7), PickupKind::Ammo { rounds: 2 });
+        let pings = radar::radar_ping(&state, &Point::new(5, 5), "earth", &None);
+
+        assert_eq!(pings.len(), 1);
+        assert_eq!(pings[0].location, Point::new(5, 7));
+        assert_eq!(
+            pings[0].kind,
+            PingKind::Pickup(PickupKind::Ammo { rounds: 2 })
+        );
+    }
+
+    #[test]
+    fn contested_pickup_stays_on_board() {
+        let mechs = vec![("al", Point::new(5, 5)), ("bob", Point::new(7, 5))];
+        let state = gen_ruleset_state(
+            mechs,
+            Ruleset {
+                secondary_ammo: Some(1),
+                ..Default::default()
+            },
+        );
+        let state = place_pickup(state, Point::new(6, 5), PickupKind::Ammo { rounds: 2 });
+        let orders = vec![
+            (
+                "al".to_string(),
+                vec![MechCommand::Move {
+                    turn: 0,
+                    mech: "al".to_string(),
+                    direction: GridDirection::East,
+                }],
+            ),
+            (
+                "bob".to_string(),
+                vec![MechCommand::Move {
+                    turn: 0,
+                    mech: "bob".to_string(),
+                    direction: GridDirection::West,
+                }],
+            ),
+        ];
+        let evts = Match::resolve_simultaneous(&state, &orders).unwrap();
+        let state = evts.iter().fold(state, |state, (_, evt)| {
+            Match::apply_event(&state, evt).unwrap()
+        });
+
+        assert_eq!(state.pickups.len(), 1);
+        assert_eq!(state.mechs["al"].ammo[&WeaponType::Secondary], 1);
+        assert_eq!(state.mechs["bob"].ammo[&WeaponType::Secondary], 1);
+    }
+
+    fn next_turn(state: MatchState) -> MatchState {
+        let evt = GameEvent::MatchTurnCompleted {
+            new_turn: state.turn_status.current + 1,
+        };
+        Match::apply_event(&state, &evt).unwrap()
+    }
+
+    #[test]
+    fn weapons_run_out_of_ammo() {
+        let state = gen_ruleset_state(
+            vec![("al", Point::new(5, 5)), ("bob", Point::new(5, 15))],
+            Ruleset {
+                secondary_ammo: Some(1),
+                ..Default::default()
+            },
+        );
+        let cmd = MechCommand::FireSecondary {
+            turn: 0,
+            mech: "al".to_string(),
+            direction: GridDirection::East,
+        };
+        let state = apply_command(state, &cmd);
+        assert_eq!(state.mechs["al"].ammo[&WeaponType::Secondary], 0);
+
+        let state = next_turn(state);
+        let evts = Match::handle_command(&state, &cmd).unwrap();
+        match &evts[..] {
+            [GameEvent::WeaponFireRejected {
+                weapon: WeaponType::Secondary,
+                reason: WeaponUnavailable::OutOfAmmo,
+                ..
+            }] => {}
+            evts => panic!("unexpected events {:?}", evts),
+        }
+
+        // unlimited primary still fires
+        let state = fire_primary(state, "al", GridDirection::East);
+        let state = fire_primary(state, "al", GridDirection::East);
+        assert_eq!(state.mechs["al"].remaining_aps, 0);
+    }
+
+    #[test]
+    fn weapons_cool_down_between_shots() {
+        let state = gen_ruleset_state(
+            vec![("al", Point::new(5, 5)), ("bob", Point::new(5, 7))],
+            Ruleset {
+                primary_cooldown: 1,
+                ..Default::default()
+            },
+        );
+        let state = fire_primary(state, "al", GridDirection::North);
+        assert_eq!(state.mechs["bob"].health, INITIAL_HEALTH - PRIMARY_DAMAGE);
+        assert_eq!(state.mechs["al"].cooldowns[&WeaponType::Primary], 2);
+
+        // can't fire again this turn or the next
+        let state = fire_primary(state, "al", GridDirection::North);
+        let state = next_turn(state);
+        let state = fire_primary(state, "al", GridDirection::North);
+        assert_eq!(state.mechs["bob"].health, INITIAL_HEALTH - PRIMARY_DAMAGE);
+        assert_eq!(state.mechs["al"].remaining_aps, 4);
+
+        let state = next_turn(state);
+        let state = fire_primary(state, "al", GridDirection::North);
+        assert_eq!(
+            state.mechs["bob"].health,
+            INITIAL_HEALTH - 2 * PRIMARY_DAMAGE
+        );
+    }
+
+    fn rotate(state: MatchState, mech: &str, direction: GridDirection) -> MatchState {
+        let cmd = MechCommand::Rotate {
+            turn: 0,
+            mech: mech.to_string(),
+            direction,
+        };
+        apply_command(state, &cmd)
+    }
+
+    #[test]
+    fn firing_restricted_to_facing_arc() {
+        let state = gen_ruleset_state(
+            vec![("al", Point::new(5, 5)), ("bob", Point::new(7, 5))],
+            Ruleset {
+                facing_arc: Some(90),
+                ..Default::default()
+            },
+        );
+        let cmd = MechCommand::FirePrimary {
+            turn: 0,
+            mech: "al".to_string(),
+            direction: GridDirection::East,
+        };
+        match &Match::handle_command(&state, &cmd).unwrap()[..] {
+            [GameEvent::WeaponFireRejected {
+                reason: WeaponUnavailable::OutsideFiringArc,
+                ..
+            }] => {}
+            evts => panic!("unexpected events {:?}", evts),
+        }
+
+        // north east is still inside a 90 degree arc facing north
+        let state = fire_primary(state, "al", GridDirection::NorthEast);
+        assert_eq!(state.mechs["al"].remaining_aps, 2);
+
+        let state = rotate(state, "al", GridDirection::East);
+        assert_eq!(state.mechs["al"].facing, GridDirection::East);
+        assert_eq!(state.mechs["al"].remaining_aps, 1);
+        let state = next_turn(state);
+        let state = fire_primary(state, "al", GridDirection::East);
+        assert_eq!(state.mechs["bob"].health, INITIAL_HEALTH - PRIMARY_DAMAGE);
+    }
+
+    #[test]
+    fn radar_restricted_to_facing_arc() {
+        let state = gen_ruleset_state(
+            vec![
+                ("al", Point::new(5, 5)),
+                ("bob", Point::new(5, 8)),
+                ("steve", Point::new(5, 2)),
+            ],
+            Ruleset {
+                facing_arc: Some(90),
+                ..Default::default()
+            },
+        );
+        let cmd = MechCommand::RequestRadarScan {
+            turn: 0,
+            mech: "al".to_string(),
+            direction: None,
+        };
+        let state = apply_command(state, &cmd);
+        let pings = &state.radar_pings["al"];
+        assert_eq!(pings.len(), 1);
+        assert_eq!(pings[0].location, Point::new(5, 8));
+    }
+
+    #[test]
+    fn shield_absorbs_damage_until_next_turn() {
+        let state = gen_root_state(
+            vec![("al", Point::new(5, 5)), ("bob", Point::new(5, 7))],
+            10,
+        );
+        let state = apply_command(
+            state,
+            &MechCommand::RaiseShield {
+                turn: 0,
+                mech: "bob".to_string(),
+            },
+        );
+        assert_eq!(state.mechs["bob"].remaining_aps, 4 - SHIELD_COST);
+
+        let state = fire_primary(state, "al", GridDirection::North);
+        assert_eq!(
+            state.mechs["bob"].health,
+            INITIAL_HEALTH - PRIMARY_DAMAGE * (100 - SHIELD_ABSORPTION) / 100
+        );
+
+        // the shield stays up through the next turn until bob's own part of it begins
+        let state = next_turn(state);
+        let health = state.mechs["bob"].health;
+        let state = fire_primary(state, "al", GridDirection::North);
+        assert!(state.mechs["bob"].health > health - PRIMARY_DAMAGE);
+        let state = Match::begin_mech_turn(&state, "bob")
+            .iter()
+            .fold(state, |state, evt| Match::apply_event(&state, evt).unwrap());
+        assert_eq!(state.mechs["bob"].shield_raised, None);
+        let health = state.mechs["bob"].health;
+        let state = fire_primary(state, "al", GridDirection::North);
+        assert_eq!(state.mechs["bob"].health, health - PRIMARY_DAMAGE);
+    }
+
+    #[test]
+    fn brace_negates_collision_damage() {
+        let state = gen_root_state(
+            vec![("al", Point::new(0, 0)), ("bob", Point::new(1, 0))],
+            10,
+        );
+        let state = apply_command(
+            state,
+            &MechCommand::Brace {
+                turn: 0,
+                mech: "al".to_string(),
+            },
+        );
+        let cmd = MechCommand::Move {
+            turn: 0,
+            mech: "al".to_string(),
+            direction: GridDirection::East,
+        };
+        let evts = Match::handle_command(&state, &cmd).unwrap();
+        assert!(evts.iter().any(|evt| match evt {
+            GameEvent::DamageBlocked { mech, damage, .. } => mech == "al" && *damage == WALL_DAMAGE,
+            _ => false,
+        }));
+        let state = apply_command(state, &cmd);
+        let state = move_mech(state, "al", GridDirection::South);
+        assert_eq!(state.mechs["al"].health, INITIAL_HEALTH);
+    }
+
+    #[test]
+    fn move_to_bounded_by_action_points() {
+        let state = gen_root_state(vec![("al", Point::new(1, 1))], 10);
+        let state = apply_command(
+            state,
+            &MechCommand::MoveTo {
+                turn: 0,
+                mech: "al".to_string(),
+                target: Point::new(10, 1),
+            },
+        );
+        assert_eq!(state.mechs["al"].position, Point::new(5, 1));
+        assert_eq!(state.mechs["al"].remaining_aps, 0);
+    }
+
+    #[test]
+    fn move_to_stops_on_collision() {
+        let state = gen_root_state(
+            vec![("al", Point::new(1, 1)), ("bob", Point::new(4, 1))],
+            10,
+        );
+        let state = MatchState::modify_mech(&state, "bob", |m| MechState {
+            position: Point::new(3, 1),
+            ..m
+        });
+        let state = apply_command(
+            state,
+            &MechCommand::MoveTo {
+                turn: 0,
+                mech: "al".to_string(),
+                target: Point::new(3, 1),
+            },
+        );
+        assert_eq!(state.mechs["al"].position, Point::new(2, 1));
+        assert_eq!(state.mechs["al"].health, INITIAL_HEALTH - WALL_DAMAGE);
+        assert_eq!(state.mechs["al"].remaining_aps, 2);
+    }
+
+    #[test]
+    fn view_hides_other_mechs() {
+        let state = gen_root_state(
+            vec![("al", Point::new(5, 5)), ("bob", Point::new(5, 7))],
+            10,
+        );
+        let state = place_pickup(state, Point::new(9, 9), PickupKind::Ammo { rounds: 2 });
+        let scan = |mech: &str| MechCommand::RequestRadarScan {
+            turn: 0,
+            mech: mech.to_string(),
+            direction: None,
+        };
+        let state = apply_command(state, &scan("al"));
+        let state = apply_command(state, &scan("bob"));
+
+        let view = MatchState::view_for(&state, "al");
+        assert_eq!(view.mechs.len(), 1);
+        assert!(view.mechs.contains_key("al"));
+        assert_eq!(view.radar_pings.len(), 1);
+        assert_eq!(view.radar_pings["al"][0].location, Point::new(5, 7));
+        assert!(view.pickups.is_empty());
+        assert_eq!(view.parameters.actors, state.parameters.actors);
+    }
+
+    #[test]
+    fn view_hides_seed_teams_and_order() {
+        let mut params = gen_parameters(
+            &[
+                ("al", Point::new(5, 5)),
+                ("bob", Point::new(5, 7)),
+                ("carl", Point::new(8, 8)),
+            ],
+            10,
+        );
+        params.seed = 1234;
+        params.teams = vec![("al", "red"), ("bob", "blue"), ("carl", "red")]
+            .into_iter()
+            .map(|(m, t)| (m.to_string(), t.to_string()))
+            .collect();
+        let mut state = gen_root_state_with_parameters(
+            vec![
+                ("al", Point::new(5, 5)),
+                ("bob", Point::new(5, 7)),
+                ("carl", Point::new(8, 8)),
+            ],
+            params,
+        );
+        state = Match::begin_turn(&state, 0)
+            .iter()
+            .fold(state.clone(), |state, evt| {
+                Match::apply_event(&state, evt).unwrap()
+            });
+        state.turn_status.taken.insert("bob".to_string());
+        assert_eq!(state.turn_status.order.len(), 3);
+
+        let view = MatchState::view_for(&state, "al");
+        assert_eq!(view.parameters.seed, 0);
+        assert_eq!(view.parameters.teams.len(), 1);
+        assert_eq!(view.parameters.teams["al"], "red");
+        assert!(view.turn_status.order.is_empty());
+        assert!(view.turn_status.taken.is_empty());
+        assert_eq!(view.turn_status.current, state.turn_status.current);
+        assert_eq!(view.parameters.ruleset, state.parameters.ruleset);
+        assert_eq!(view.game_board.width, state.game_board.width);
+    }
+
+    #[test]
+    fn spectator_view_hides_secrets() {
+        let mut params = gen_parameters(&[("al", Point::new(5, 5)), ("bob", Point::new(5, 7))], 10);
+        params.seed = 1234;
+        let state = gen_root_state_with_parameters(
+            vec![("al", Point::new(5, 5)), ("bob", Point::new(5, 7))],
+            params,
+        );
+        let state = place_pickup(state, Point::new(9, 9), PickupKind::Ammo { rounds: 2 });
+        let state = apply_command(
+            state,
+            &MechCommand::RequestRadarScan {
+                turn: 0,
+                mech: "al".to_string(),
+                direction: None,
+            },
+        );
+        let mut state = apply_command(
+            state,
+            &MechCommand::RegisterUpdate {
+                turn: 0,
+                mech: "al".to_string(),
+                reg: EAX,
+                op: RegisterOperation::Set(RegisterValue::Number(7)),
+            },
+        );
+        state.turn_status.order = vec!["bob".to_string(), "al".to_string()];
+
+        let view = MatchState::spectator_view(&state);
+        assert_eq!(view.parameters.seed, 0);
+        assert_eq!(view.mechs.len(), 2);
+        assert_eq!(view.mechs["bob"].position, Point::new(5, 7));
+        assert_eq!(view.mechs["al"].health, state.mechs["al"].health);
+        assert!(view.mechs["al"].registers.is_empty());
+        assert!(view.radar_pings.is_empty());
+        assert!(view.pickups.is_empty());
+        assert!(view.turn_status.order.is_empty());
+    }
+
+    #[test]
+    fn actor_commands_validated() {
+        let state = gen_root_state(
+            vec![("al", Point::new(5, 5)), ("bob", Point::new(5, 7))],
+            10,
+        );
+        let violation =
+            |actor: &str, cmd: MechCommand| match &Match::handle_actor_command(&state, actor, &cmd)
+                .unwrap()[..]
+            {
+                [GameEvent::CommandViolated { violation, .. }] => Some(violation.clone()),
+                _ => None,
+            };
+
+        let moving = |mech: &str, turn: u32| MechCommand::Move {
+            turn,
+            mech: mech.to_string(),
+            direction: GridDirection::East,
+        };
+        assert_eq!(violation("al", moving("al", 0)), None);
+        assert_eq!(
+            violation("al", moving("bob", 0)),
+            Some(CommandViolation::NotOwner)
+        );
+        assert_eq!(
+            violation("al", moving("al", 3)),
+            Some(CommandViolation::WrongTurn { current: 0 })
+        );
+        assert_eq!(
+            violation(
+                "al",
+                MechCommand::SpawnMech {
+                    mech: "al".to_string(),
+                    position: Point::new(1, 1),
+                    team: "earth".to_string(),
+                    avatar: "none".to_string(),
+                    name: "al".to_string(),
+                }
+            ),
+            Some(CommandViolation::Forbidden)
+        );
+    }
+
+    #[test]
+    fn simultaneous_orders_validated() {
+        let state = gen_root_state(
+            vec![("al", Point::new(5, 5)), ("bob", Point::new(5, 7))],
+            10,
+        );
+        let orders = vec![(
+            "al".to_string(),
+            vec![MechCommand::Move {
+                turn: 0,
+                mech: "bob".to_string(),
+                direction: GridDirection::East,
+            }],
+        )];
+        let evts = Match::resolve_simultaneous(&state, &orders).unwrap();
+        let state = evts.iter().fold(state, |state, (_, evt)| {
+            Match::apply_event(&state, evt).unwrap()
+        });
+
+        assert_eq!(state.mechs["bob"].position, Point::new(5, 7));
+        assert!(evts
+            .iter()
+            .any(|(actor, evt)| actor == "al" && matches!(evt, GameEvent::CommandViolated { .. })));
+    }
+
+    #[test]
+    fn commands_for_unknown_mechs_rejected() {
+        let state = gen_root_state(vec![("al", Point::new(5, 5))], 10);
+        let cmds = vec![
+            MechCommand::RequestRadarScan {
+                turn: 0,
+                mech: "nobody".to_string(),
+                direction: None,
+            },
+            MechCommand::Move {
+                turn: 0,
+                mech: "nobody".to_string(),
+                direction: GridDirection::East,
+            },
+            MechCommand::RegisterUpdate {
+                turn: 0,
+                mech: "nobody".to_string(),
+                reg: EAX,
+                op: RegisterOperation::Accumulate(1),
+            },
+        ];
+        for cmd in cmds {
+            let evts = Match::handle_command(&state, &cmd).unwrap();
+            assert!(matches!(&evts[..], [GameEvent::CommandRejected { .. }]));
+        }
+    }
 }
@@ -0,0 +1,321 @@
+//! Plays a match a turn at a time. The engine and the in-process match runner both play
+//! their matches through here, so a match plays out the same wherever it is hosted. The
+//! host decides how mechs are asked for their commands and what becomes of the events
+
+use crate::{
+    commands::MechCommand,
+    events::GameEvent,
+    state::{Match, MatchState},
+    Point, SeededRng, TurnMode,
+};
+use eventsourcing::{Aggregate, Result};
+
+/// Actor to which events raised by the arena itself, rather than a mech, are attributed
+pub const ARENA_ACTOR: &str = "arena";
+
+/// What came of asking a mech to take its turn
+#[derive(Debug, Clone)]
+pub enum TurnResponse {
+    Taken(Vec<MechCommand>),
+    /// The mech didn't answer within the match's turn timeout
+    TimedOut,
+    /// The mech couldn't be asked, or its answer couldn't be understood
+    Failed,
+}
+
+/// A mech entering a match, before it has a place on the board
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entrant {
+    pub id: String,
+    pub team: String,
+    pub avatar: String,
+    pub name: String,
+}
+
+/// Connects a match to the mechs playing in it and to whoever follows its events
+pub trait MatchHost {
+    /// Asks each of the given mechs for its commands for the turn, all working from the
+    /// same state, returning their responses in the same order. Mechs that don't answer
+    /// within the match's turn timeout have timed out
+    fn request_turns(
+        &mut self,
+        state: &MatchState,
+        mechs: &[String],
+        turn: u32,
+    ) -> Vec<TurnResponse>;
+
+    /// Called with each event once it has been applied to the match, along with the
+    /// actor it is attributed to
+    fn event_applied(&mut self, actor: &str, turn: u32, evt: &GameEvent);
+}
+
+/// Picks a spot for a mech to spawn, clear of the edges of the board
+pub fn random_spawnpoint(rng: &mut SeededRng, board_height: u32, board_width: u32) -> Point {
+    let x: u32 = rng.between(1, board_width - 1);
+    let y: u32 = rng.between(1, board_height - 1);
+    Point::new(x as _, y as _)
+}
+
+/// Spawns the mechs at random points drawn from the match's seed. Mechs spawn in the
+/// order of their ids, so every run of a seed puts the same mechs in the same places
+pub fn spawn_mechs(
+    host: &mut impl MatchHost,
+    state: &MatchState,
+    entrants: &[Entrant],
+) -> Result<MatchState> {
+    let mut entrants = entrants.to_vec();
+    entrants.sort_by(|a, b| a.id.cmp(&b.id));
+    let mut rng = SeededRng::for_stream(state.parameters.seed, "spawn");
+    let mut state = state.clone();
+    for mech in entrants {
+        let cmd = MechCommand::SpawnMech {
+            mech: mech.id.to_string(),
+            position: random_spawnpoint(&mut rng, state.parameters.height, state.parameters.width),
+            team: mech.team,
+            avatar: mech.avatar,
+            name: mech.name,
+        };
+        let evts = Match::handle_command(&state, &cmd)?;
+        state = apply(host, &state, &mech.id, 0, evts)?;
+    }
+    Ok(state)
+}
+
+/// Plays a turn of the match: drops any pickups due onto the board, asks the mechs for
+/// their commands as the match's turn mode calls for, and then completes the turn on
+/// behalf of any mech that didn't finish it, so that the match keeps moving
+pub fn play_turn(host: &mut impl MatchHost, state: &MatchState, turn: u32) -> Result<MatchState> {
+    let mut state = apply(
+        host,
+        state,
+        ARENA_ACTOR,
+        turn,
+        Match::spawn_pickups(state, turn),
+    )?;
+    match state.parameters.turn_mode {
+        TurnMode::Sequential => {
+            for evt in Match::begin_turn(&state, turn) {
+                let first = match &evt {
+                    // attributed to whichever mech gets to move first
+                    GameEvent::TurnOrderDetermined { order, .. } => order.first().cloned(),
+                    _ => None,
+                };
+                let actor = first.unwrap_or_else(|| ARENA_ACTOR.to_string());
+                state = apply(host, &state, &actor, turn, vec![evt])?;
+            }
+            for mech in state.turn_status.order.clone() {
+                state = apply(
+                    host,
+                    &state,
+                    &mech,
+                    turn,
+                    Match::begin_mech_turn(&state, &mech),
+                )?;
+                let response = host
+                    .request_turns(&state, &[mech.to_string()], turn)
+                    .pop()
+                    .unwrap_or(TurnResponse::Failed);
+                state = match response {
+                    TurnResponse::Taken(cmds) => cmds.iter().try_fold(state, |state, cmd| {
+                        let evts = Match::handle_actor_command(&state, &mech, cmd)?;
+                        apply(host, &state, &mech, turn, evts)
+                    })?,
+                    TurnResponse::TimedOut => apply(
+                        host,
+                        &state,
+                        ARENA_ACTOR,
+                        turn,
+                        Match::time_out(&state, &mech),
+                    )?,
+                    TurnResponse::Failed => state,
+                };
+            }
+        }
+        TurnMode::Simultaneous => {
+            // Every mech plans its turn from the same snapshot
+            let mechs: Vec<_> = state
+                .parameters
+                .actors
+                .iter()
+                .filter(|pk| state.mechs.get(*pk).map_or(false, |m| m.alive))
+                .cloned()
+                .collect();
+            for mech in &mechs {
+                state = apply(
+                    host,
+                    &state,
+                    mech,
+                    turn,
+                    Match::begin_mech_turn(&state, mech),
+                )?;
+            }
+            let responses = host.request_turns(&state, &mechs, turn);
+            let mut orders = Vec::new();
+            let mut timed_out = Vec::new();
+            for (mech, response) in mechs.into_iter().zip(responses) {
+                match response {
+                    TurnResponse::Taken(cmds) => orders.push((mech, cmds)),
+                    TurnResponse::TimedOut => timed_out.push(mech),
+                    TurnResponse::Failed => {}
+                }
+            }
+            for (actor, evt) in Match::resolve_simultaneous(&state, &orders)? {
+                state = apply(host, &state, &actor, turn, vec![evt])?;
+            }
+            for mech in timed_out {
+                state = apply(
+                    host,
+                    &state,
+                    ARENA_ACTOR,
+                    turn,
+                    Match::time_out(&state, &mech),
+                )?;
+            }
+        }
+    }
+    finish_outstanding_turns(host, &state, turn)
+}
+
+/// Whether the match has ended, or has played all of its turns
+pub fn match_over(state: &MatchState) -> bool {
+    state.completed.is_some() || state.turn_status.current > state.parameters.max_turns
+}
+
+/// Completes the turn on behalf of any mech that didn't finish it, whether it failed to
+/// respond or its own `FinishTurn` was refused
+fn finish_outstanding_turns(
+    host: &mut impl MatchHost,
+    state: &MatchState,
+    turn: u32,
+) -> Result<MatchState> {
+    let mut state = state.clone();
+    for mech in state.parameters.actors.clone() {
+        if state.completed.is_some()
+            || state.turn_status.current != turn
+            || state.turn_status.taken.contains(&mech)
+        {
+            continue;
+        }
+        let evts = Match::handle_command(&state, &MechCommand::FinishTurn { mech, turn })?;
+        state = apply(host, &state, ARENA_ACTOR, turn, evts)?;
+    }
+    Ok(state)
+}
+
+/// Applies events to the match, letting the host know about each as it goes
+fn apply(
+    host: &mut impl MatchHost,
+    state: &MatchState,
+    actor: &str,
+    turn: u32,
+    evts: Vec<GameEvent>,
+) -> Result<MatchState> {
+    evts.iter().try_fold(state.clone(), |state, evt| {
+        let state = Match::apply_event(&state, evt)?;
+        host.event_applied(actor, turn, evt);
+        Ok(state)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MatchParameters, Ruleset, TurnOrder};
+    use std::collections::HashMap;
+
+    /// Mechs that answer with no commands at all, apart from those that never answer
+    struct IdleHost {
+        unresponsive: Vec<String>,
+        events: Vec<(String, GameEvent)>,
+    }
+
+    impl MatchHost for IdleHost {
+        fn request_turns(
+            &mut self,
+            _state: &MatchState,
+            mechs: &[String],
+            _turn: u32,
+        ) -> Vec<TurnResponse> {
+            mechs
+                .iter()
+                .map(|m| {
+                    if self.unresponsive.contains(m) {
+                        TurnResponse::TimedOut
+                    } else {
+                        TurnResponse::Taken(vec![])
+                    }
+                })
+                .collect()
+        }
+
+        fn event_applied(&mut self, actor: &str, _turn: u32, evt: &GameEvent) {
+            self.events.push((actor.to_string(), evt.clone()));
+        }
+    }
+
+    fn entrant(id: &str) -> Entrant {
+        Entrant {
+            id: id.to_string(),
+            team: "earth".to_string(),
+            avatar: "none".to_string(),
+            name: id.to_string(),
+        }
+    }
+
+    fn play(turn_mode: TurnMode) -> (MatchState, IdleHost) {
+        let params = MatchParameters::new(
+            "test".to_string(),
+            10,
+            10,
+            5,
+            4,
+            vec!["al".to_string(), "bob".to_string()],
+            Ruleset {
+                max_timeouts: 3,
+                ..Ruleset::default()
+            },
+            vec![],
+            turn_mode,
+            TurnOrder::Fixed,
+            7,
+            HashMap::new(),
+        );
+        let mut host = IdleHost {
+            unresponsive: vec!["bob".to_string()],
+            events: Vec::new(),
+        };
+        let state = MatchState::new_with_parameters(params);
+        let mut state = spawn_mechs(&mut host, &state, &[entrant("bob"), entrant("al")]).unwrap();
+        let mut turn = 0;
+        while !match_over(&state) {
+            state = play_turn(&mut host, &state, turn).unwrap();
+            turn += 1;
+        }
+        (state, host)
+    }
+
+    #[test]
+    fn plays_every_turn_in_either_mode() {
+        for mode in &[TurnMode::Sequential, TurnMode::Simultaneous] {
+            let (state, host) = play(*mode);
+            // bob is ejected once it has timed out too often, leaving al the winner
+            assert!(state.completed.is_some());
+            assert!(!state.mechs["bob"].alive);
+            // mechs are spawned in the order of their ids
+            assert!(matches!(
+                &host.events[0],
+                (actor, GameEvent::MechSpawned { mech, .. }) if actor == "al" && mech == "al"
+            ));
+            assert!(host.events.iter().any(|(actor, evt)| actor == ARENA_ACTOR
+                && matches!(evt, GameEvent::TurnTimedOut { mech, .. } if mech == "bob")));
+        }
+    }
+
+    #[test]
+    fn spawns_are_drawn_from_the_seed() {
+        let (a, _) = play(TurnMode::Sequential);
+        let (b, _) = play(TurnMode::Simultaneous);
+        assert_eq!(a.mechs["al"].position, b.mechs["al"].position);
+        assert_eq!(a.mechs["bob"].position, b.mechs["bob"].position);
+    }
+}
@@ -2,111 +2,90 @@ use crate::store::MatchStore;
 use chrono::prelude::*;
 use domain::eventsourcing::Aggregate;
 use domain::{
-    commands::MechCommand,
-    events::GameEvent,
+    events::{EndCause, GameEvent},
     state::{Match, MatchState},
-    Point,
+    turns::{self, MatchHost, TurnResponse, ARENA_ACTOR},
 };
-use protocol::MechInfo;
 use protocol::{
     commands::{TakeTurn, TakeTurnResponse},
     events::{ArenaEvent, MatchEvent},
     OP_TAKE_TURN,
 };
 use std::{
-    sync::{Arc, RwLock},
-    time::Duration,
+    sync::{mpsc, Arc, RwLock},
+    time::{Duration, Instant},
 };
 use wascc_codec::{capabilities::Dispatcher, deserialize, serialize};
 
-pub(crate) fn spawn_mechs(
-    nc: Arc<nats::Connection>,
-    state: MatchState,
-    actors: Vec<MechInfo>,
-) -> MatchState {
-    let mut state = state.clone();
-    for mech in actors {
-        let cmd = MechCommand::SpawnMech {
-            mech: mech.id.to_string(),
-            position: random_spawnpoint(state.parameters.height, state.parameters.width),
-            team: mech.team.to_string(),
-            avatar: mech.avatar.to_string(),
-            name: mech.name.to_string(),
-        };
-        for event in Match::handle_command(&state, &cmd).unwrap() {
-            nc.publish(
-                &protocol::events::events_subject(Some(&state.parameters.match_id)),
-                &serde_json::to_vec(&turn_event(&event, &state.parameters.match_id, &mech.id))
-                    .unwrap(),
-            )
-            .unwrap();
-            state = Match::apply_event(&state, &event).unwrap();
-        }
-    }
-    state
+/// Hosts matches on the lattice, dispatching turns to the mechs' actors and publishing
+/// each event of the match as it is applied
+pub(crate) struct ArenaHost {
+    pub(crate) nc: Arc<nats::Connection>,
+    pub(crate) dispatcher: Arc<RwLock<Arc<dyn Dispatcher>>>,
+    pub(crate) match_id: String,
 }
 
-pub(crate) fn turn_event(evt: &GameEvent, match_id: &str, actor: &str) -> MatchEvent {
-    MatchEvent::TurnEvent {
-        actor: actor.to_string(),
-        match_id: match_id.to_string(),
-        turn_event: evt.clone(),
-        turn: 0,
+impl MatchHost for ArenaHost {
+    /// Dispatches the turn to every mech at once, then collects the responses against a
+    /// single deadline
+    fn request_turns(
+        &mut self,
+        state: &MatchState,
+        mechs: &[String],
+        turn: u32,
+    ) -> Vec<TurnResponse> {
+        let pending: Vec<_> = mechs
+            .iter()
+            .map(|pk| dispatch_turn(self.dispatcher.clone(), pk, state, turn))
+            .collect();
+        let deadline = turn_deadline(state);
+        mechs
+            .iter()
+            .zip(pending)
+            .map(|(pk, rx)| await_turn(rx, pk, turn, deadline))
+            .collect()
     }
-}
 
-pub(crate) fn random_spawnpoint(board_height: u32, board_width: u32) -> Point {
-    use rand::Rng;
-    let mut rng = rand::thread_rng();
-    let x: u32 = rng.gen_range(1, board_width);
-    let y: u32 = rng.gen_range(1, board_height);
-    Point::new(x as _, y as _)
+    fn event_applied(&mut self, actor: &str, turn: u32, evt: &GameEvent) {
+        publish_event(self.nc.clone(), actor, &self.match_id, turn, evt);
+    }
 }
 
 pub(crate) fn manage_match(
     nc: Arc<nats::Connection>,
-    dispatcher: Arc<RwLock<Box<dyn Dispatcher>>>,
+    dispatcher: Arc<RwLock<Arc<dyn Dispatcher>>>,
     store: Arc<RwLock<MatchStore>>,
-    actors: Vec<String>,
     match_id: String,
     turn_delay_millis: u64,
 ) {
     info!("Starting thread to manage match {}", match_id);
     std::thread::spawn(move || {
+        let mut host = ArenaHost {
+            nc: nc.clone(),
+            dispatcher,
+            match_id: match_id.to_string(),
+        };
         let mut match_complete = false;
         let mut turn = 0;
         while !match_complete {
-            let mut state = store.read().unwrap().get_match_state(&match_id).unwrap();
-            for pk in &actors {
-                let tt = TakeTurn {
-                    actor: pk.to_string(),
-                    match_id: match_id.to_string(),
-                    turn,
-                    state: state.clone(),
-                };
-                let mech_turn_result =
-                    dispatcher
-                        .read()
+            let state = store.read().unwrap().get_match_state(&match_id).unwrap();
+            let state = match turns::play_turn(&mut host, &state, turn) {
+                Ok(state) => {
+                    store
+                        .write()
                         .unwrap()
-                        .dispatch(pk, OP_TAKE_TURN, &serialize(tt).unwrap());
-                match mech_turn_result {
-                    Ok(tr) => {
-                        let tr: TakeTurnResponse = deserialize(&tr).unwrap();
-                        state = process_turn_response(
-                            nc.clone(),
-                            pk,
-                            tr,
-                            store.clone(),
-                            &state,
-                            &match_id,
-                            turn,
-                        );
-                    }
-                    Err(e) => error!("Failed to get turn actions from actor {}: {}", pk, e),
+                        .save_match_state(&match_id, state.clone())
+                        .unwrap();
+                    state
                 }
-            }
+                Err(e) => {
+                    error!("Failed to play turn {} of match {}: {}", turn, match_id, e);
+                    state
+                }
+            };
             turn = turn + 1;
-            match_complete = check_match_over(&state);
+            let (state, complete) = check_match_over(nc.clone(), store.clone(), &state, turn);
+            match_complete = complete;
             if match_complete {
                 publish_match_complete(nc.clone(), &state);
                 info!("Match {} completed", match_id);
@@ -129,53 +108,118 @@ fn publish_match_complete(nc: Arc<nats::Connection>, state: &MatchState) {
     .unwrap();
 }
 
-fn check_match_over(state: &MatchState) -> bool {
-    state.completed.is_some() || (state.turn_status.current > state.parameters.max_turns)
-}
-
-fn process_turn_response(
+/// Ends the match if an abort was requested or mechs have dropped out of the arena,
+/// returning the updated state and whether the match is over
+fn check_match_over(
     nc: Arc<nats::Connection>,
-    actor: &str,
-    resp: TakeTurnResponse,
     store: Arc<RwLock<MatchStore>>,
     state: &MatchState,
-    match_id: &str,
     turn: u32,
-) -> MatchState {
-    let state = state.clone();
-    let state = resp.commands.into_iter().fold(state, |state, cmd| {
-        apply_command(nc.clone(), &state, cmd, actor, turn, match_id)
+) -> (MatchState, bool) {
+    let match_id = &state.parameters.match_id;
+    // the store's guard must be released before the match below takes it again
+    let abort = store.write().unwrap().take_abort_request(match_id);
+    let evts = match abort {
+        Ok(Some(reason)) => Match::end_match(state, EndCause::Aborted { reason }),
+        _ => {
+            let connected: Vec<String> = store
+                .write()
+                .unwrap()
+                .bound_actors()
+                .map(|ba| ba.into_iter().map(|mi| mi.id).collect())
+                .unwrap_or_default();
+            Match::check_connections(state, &connected)
+        }
+    };
+    let state = evts.iter().fold(state.clone(), |state, evt| {
+        publish_event(nc.clone(), ARENA_ACTOR, match_id, turn, evt);
+        match Match::apply_event(&state, evt) {
+            Ok(state) => state,
+            Err(e) => {
+                error!("Event processing failure: {}", e);
+                state
+            }
+        }
     });
-    store
-        .write()
-        .unwrap()
-        .save_match_state(match_id, state.clone())
-        .unwrap();
-    state
+    if !evts.is_empty() {
+        store
+            .write()
+            .unwrap()
+            .save_match_state(match_id, state.clone())
+            .unwrap();
+    }
+    let complete = turns::match_over(&state);
+    (state, complete)
 }
 
-fn apply_command(
-    nc: Arc<nats::Connection>,
+/// Returns the moment by which mechs asked for their turn now must respond, if the
+/// match has a turn timeout
+fn turn_deadline(state: &MatchState) -> Option<Instant> {
+    match state.parameters.ruleset.turn_timeout_millis {
+        0 => None,
+        timeout => Some(Instant::now() + Duration::from_millis(timeout)),
+    }
+}
+
+/// Sends a mech its turn on a thread of its own, so that a mech stuck in a loop can't
+/// stall the arena. The waSCC host has no way to interrupt a running actor, so a mech
+/// that never returns keeps its thread for as long as the host runs. The thread holds its
+/// own handle to the dispatcher rather than a lock on it, so a stuck mech never keeps a
+/// new dispatcher from being configured. Mechs that keep timing out are ejected, which
+/// bounds how many such threads a match can leave behind
+fn dispatch_turn(
+    dispatcher: Arc<RwLock<Arc<dyn Dispatcher>>>,
+    actor: &str,
     state: &MatchState,
-    cmd: MechCommand,
+    turn: u32,
+) -> mpsc::Receiver<Result<Vec<u8>, String>> {
+    let tt = TakeTurn::new(state, actor, turn); // mechs only get to see what their sensors tell them
+    let (tx, rx) = mpsc::channel();
+    let (pk, msg) = (actor.to_string(), serialize(tt).unwrap());
+    let dispatcher = dispatcher.read().unwrap().clone();
+    std::thread::spawn(move || {
+        let res = dispatcher
+            .dispatch(&pk, OP_TAKE_TURN, &msg)
+            .map_err(|e| e.to_string());
+        let _ = tx.send(res); // nobody is listening if the turn already timed out
+    });
+    rx
+}
+
+/// Waits for a mech's response to a dispatched turn until the deadline, if there is one
+fn await_turn(
+    pending: mpsc::Receiver<Result<Vec<u8>, String>>,
     actor: &str,
     turn: u32,
-    match_id: &str,
-) -> MatchState {
-    let state = state.clone();
-    Match::handle_command(&state, &cmd)
-        .unwrap()
-        .iter()
-        .fold(state, |state, evt| {
-            publish_event(nc.clone(), actor, match_id, turn, evt); // This is so side-effecty. Fix this.
-            match Match::apply_event(&state, evt) {
-                Ok(evt) => evt,
-                Err(e) => {
-                    error!("Event processing failure: {}", e);
-                    state
-                }
+    deadline: Option<Instant>,
+) -> TurnResponse {
+    let res = match deadline {
+        None => pending
+            .recv()
+            .map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+        Some(deadline) => pending.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+    };
+    match res {
+        Ok(Ok(tr)) => match deserialize::<TakeTurnResponse>(&tr) {
+            Ok(tr) => TurnResponse::Taken(tr.commands),
+            Err(e) => {
+                error!("Unreadable turn response from actor {}: {}", actor, e);
+                TurnResponse::Failed
             }
-        })
+        },
+        Ok(Err(e)) => {
+            error!("Failed to get turn actions from actor {}: {}", actor, e);
+            TurnResponse::Failed
+        }
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            warn!("Actor {} timed out on turn {}", actor, turn);
+            TurnResponse::TimedOut
+        }
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            error!("Lost track of turn request to actor {}", actor);
+            TurnResponse::Failed
+        }
+    }
 }
 
 fn publish_event(
@@ -201,7 +245,7 @@ fn publish_event(
 
 pub(crate) fn spawn_health_check(
     nc: Arc<nats::Connection>,
-    dispatcher: Arc<RwLock<Box<dyn Dispatcher>>>,
+    dispatcher: Arc<RwLock<Arc<dyn Dispatcher>>>,
     store: Arc<RwLock<MatchStore>>,
 ) {
     ::std::thread::spawn(move || loop {
@@ -212,7 +256,7 @@ pub(crate) fn spawn_health_check(
 
 pub(crate) fn perform_health_check(
     store: Arc<RwLock<MatchStore>>,
-    dispatcher: Arc<RwLock<Box<dyn Dispatcher>>>,
+    dispatcher: Arc<RwLock<Arc<dyn Dispatcher>>>,
     nc: Arc<nats::Connection>,
 ) {
     let ba = store.write().unwrap().bound_actors().unwrap();
@@ -20,9 +20,13 @@ use codec::capabilities::{
 use codec::core::{CapabilityConfiguration, OP_BIND_ACTOR, OP_REMOVE_ACTOR};
 use codec::{deserialize, serialize};
 use protocol::commands::*;
-use protocol::{events::ArenaEvent, OP_TAKE_TURN};
+use protocol::{
+    events::{ArenaEvent, MatchEvent},
+    OP_TAKE_TURN,
+};
 
 use domain::state::MatchState;
+use domain::turns::{self, Entrant};
 use protocol::MechInfo;
 use std::error::Error;
 use std::{
@@ -49,7 +53,7 @@ capability_provider!(WasmdomeEngineProvider, WasmdomeEngineProvider::new);
 
 pub struct WasmdomeEngineProvider {
     nc: Arc<nats::Connection>,
-    dispatcher: Arc<RwLock<Box<dyn Dispatcher>>>,
+    dispatcher: Arc<RwLock<Arc<dyn Dispatcher>>>,
     store: Arc<RwLock<MatchStore>>,
     turn_delay_millis: u64,
 }
@@ -61,7 +65,7 @@ impl Default for WasmdomeEngineProvider {
         info!("Using turn delay of {}ms", td);
 
         WasmdomeEngineProvider {
-            dispatcher: Arc::new(RwLock::new(Box::new(NullDispatcher::new()))),
+            dispatcher: Arc::new(RwLock::new(Arc::new(NullDispatcher::new()))),
             store: Arc::new(RwLock::new(MatchStore::new())),
             nc: Arc::new(get_connection()),
             turn_delay_millis: get_turn_delay(),
@@ -186,7 +190,7 @@ impl CapabilityProvider for WasmdomeEngineProvider {
     fn configure_dispatch(&self, dispatcher: Box<dyn Dispatcher>) -> Result<(), Box<dyn Error>> {
         trace!("Dispatcher received.");
         let mut lock = self.dispatcher.write().unwrap();
-        *lock = dispatcher;
+        *lock = Arc::from(dispatcher);
         let td = self.turn_delay_millis;
 
         spawn_health_check(self.nc.clone(), self.dispatcher.clone(), self.store.clone());
@@ -221,7 +225,7 @@ impl CapabilityProvider for WasmdomeEngineProvider {
 fn handle_control_command(
     ac: ArenaControlCommand,
     nc: Arc<nats::Connection>,
-    dispatcher: Arc<RwLock<Box<dyn Dispatcher>>>,
+    dispatcher: Arc<RwLock<Arc<dyn Dispatcher>>>,
     store: Arc<RwLock<MatchStore>>,
     reply: Option<String>,
     turn_delay_millis: u64,
@@ -248,13 +252,41 @@ fn handle_control_command(
             }
             None => {}
         },
+        QueryMatch(qm) => match reply {
+            Some(s) => {
+                let resp = MatchQueryResponse {
+                    state: store
+                        .read()
+                        .unwrap()
+                        .get_match_state(&qm.match_id)
+                        .ok()
+                        .map(|state| MatchState::spectator_view(&state)),
+                };
+                nc.publish(&s, &serde_json::to_vec(&resp).unwrap()).unwrap();
+            }
+            None => {}
+        },
+        AbortMatch(am) => match store
+            .write()
+            .unwrap()
+            .request_abort(&am.match_id, &am.reason)
+        {
+            Ok(_) => {
+                if let Some(s) = reply {
+                    let _ = nc.publish(&s, b"OK");
+                }
+            }
+            Err(e) => {
+                error!("Failed to abort match {}: {}", am.match_id, e);
+            }
+        },
     };
 }
 
 fn start_match(
     createmsg: CreateMatch,
     store: Arc<RwLock<MatchStore>>,
-    dispatcher: Arc<RwLock<Box<dyn Dispatcher>>>,
+    dispatcher: Arc<RwLock<Arc<dyn Dispatcher>>>,
     nc: Arc<nats::Connection>,
     turn_delay_millis: u64,
 ) -> Result<Vec<u8>, Box<dyn Error>> {
@@ -276,9 +308,39 @@ fn start_match(
         createmsg.max_turns,
         createmsg.aps_per_turn,
         remove_noshows(&createmsg.actors, &current_mech_ids), // use this instead of the match params list because this one's filtered by healthy
+        createmsg.ruleset.clone().unwrap_or_default(),
+        createmsg.terrain.clone(),
+        createmsg.turn_mode.unwrap_or_default(),
+        createmsg.turn_order.clone().unwrap_or_default(),
+        createmsg.seed.unwrap_or_else(rand::random),
+        createmsg.teams.clone(),
     );
+    params.ruleset.registers.validate()?;
     let mut state = MatchState::new_with_parameters(params.clone());
-    state = spawn_mechs(nc.clone(), state, store.write().unwrap().bound_actors()?);
+    nc.publish(
+        &protocol::events::events_subject(Some(&createmsg.match_id)),
+        &serde_json::to_vec(&MatchEvent::MatchStarted {
+            match_id: createmsg.match_id.clone(),
+            // anyone can follow the match's events, so its seed stays secret
+            parameters: MatchParameters {
+                seed: 0,
+                ..params.clone()
+            },
+        })?,
+    )?;
+    let entrants: Vec<Entrant> = store
+        .write()
+        .unwrap()
+        .bound_actors()?
+        .iter()
+        .map(Entrant::from)
+        .collect();
+    let mut host = ArenaHost {
+        nc: nc.clone(),
+        dispatcher: dispatcher.clone(),
+        match_id: createmsg.match_id.clone(),
+    };
+    state = turns::spawn_mechs(&mut host, &state, &entrants)?;
     store
         .write()
         .unwrap()
@@ -291,6 +353,8 @@ fn start_match(
             actors: params.actors.clone(),
             board_height: params.height,
             board_width: params.width,
+            terrain: params.terrain.clone(),
+            seed: params.seed,
             start_time: Utc::now(),
         })?,
     )?;
@@ -301,7 +365,6 @@ fn start_match(
         nc.clone(),
         dispatcher.clone(),
         store.clone(),
-        createmsg.actors.clone(),
         createmsg.match_id.clone(),
         turn_delay_millis,
     );
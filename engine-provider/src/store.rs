@@ -10,6 +10,7 @@ use redis::Commands;
 pub(crate) struct MatchStore {
     matches: Option<HashMap<String, MatchState>>,
     bound_actors: HashMap<String, MechInfo>,
+    aborts: HashMap<String, String>,
     client: Option<redis::Client>,
 }
 
@@ -19,6 +20,7 @@ impl Default for MatchStore {
             matches: Some(HashMap::new()),
             client: None,
             bound_actors: HashMap::new(),
+            aborts: HashMap::new(),
         }
     }
 }
@@ -39,6 +41,7 @@ impl MatchStore {
             matches: None,
             client: Some(redis::Client::open(url).unwrap()),
             bound_actors: HashMap::new(),
+            aborts: HashMap::new(),
         }
     }
 
@@ -98,6 +101,41 @@ impl MatchStore {
         }
     }
 
+    pub fn request_abort(
+        &mut self,
+        match_id: &str,
+        reason: &str,
+    ) -> Result<(), Box<dyn ::std::error::Error>> {
+        if self.client.is_none() {
+            let _ = self.aborts.insert(match_id.to_string(), reason.to_string());
+            Ok(())
+        } else {
+            self.client
+                .as_mut()
+                .unwrap()
+                .set(abort_key(match_id), reason)
+                .map(|_: ()| ())
+                .map_err(|e| e.into())
+        }
+    }
+
+    /// Retrieves and clears a pending abort request for the given match
+    pub fn take_abort_request(
+        &mut self,
+        match_id: &str,
+    ) -> Result<Option<String>, Box<dyn ::std::error::Error>> {
+        if self.client.is_none() {
+            Ok(self.aborts.remove(match_id))
+        } else {
+            let k = abort_key(match_id);
+            let reason: Option<String> = self.client.as_mut().unwrap().get(&k)?;
+            if reason.is_some() {
+                let _: bool = self.client.as_mut().unwrap().del(&k)?;
+            }
+            Ok(reason)
+        }
+    }
+
     pub fn save_match_state(
         &mut self,
         match_id: &str,
@@ -148,6 +186,10 @@ fn match_key(match_id: &str) -> String {
     format!("wasmdome:matches:{}:state", match_id)
 }
 
+fn abort_key(match_id: &str) -> String {
+    format!("wasmdome:matches:{}:abort", match_id)
+}
+
 fn actors_key() -> String {
     "wasmdome:actors".to_string()
 }
@@ -158,6 +200,7 @@ fn mechinfo_key(pk: &str) -> String {
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashMap;
     use wasmdome_protocol::commands::{ArenaControlCommand, CreateMatch};
 
     #[test]
@@ -176,6 +219,12 @@ mod test {
             board_height: 10,
             board_width: 20,
             max_turns: 100,
+            ruleset: None,
+            terrain: Vec::new(),
+            turn_mode: None,
+            turn_order: None,
+            seed: None,
+            teams: HashMap::new(),
         });
         let s2 = serde_json::to_string(&sm).unwrap();
         assert_eq!(
@@ -27,7 +27,7 @@ fn handle_message(msg: codec::messaging::BrokerMessage) -> HandlerResult<()> {
     if msg.subject == SUBJECT_TRIGGER_REPLAY {
         trigger_replay(events, msg.body)
     } else if is_match_event_subject(&msg.subject) {
-        //TODO? does not currently record arena events like actor up/down and match start/complete
+        //TODO? does not currently record arena events like actor up/down and match complete
         record_match_event(events, msg.body)
     } else {
         Ok(())
@@ -63,7 +63,9 @@ fn record_match_event(events: EventStreamsHostBinding, msg: Vec<u8>) -> HandlerR
 
 fn extract_match_id(evt: &MatchEvent) -> String {
     match evt {
-        MatchEvent::TurnEvent { match_id, .. } => match_id.to_string(),
+        MatchEvent::TurnEvent { match_id, .. } | MatchEvent::MatchStarted { match_id, .. } => {
+            match_id.to_string()
+        }
     }
 }
 
@@ -28,6 +28,7 @@ fn produce_leaderboard() -> HandlerResult<serde_json::Value> {
     let result = json!({
         "stats": state.stats,
         "mechs": state.mechs,
+        "teams": state.teams,
     });
     Ok(result)
 }
@@ -62,6 +63,7 @@ fn handle_match_event(msg: Vec<u8>) -> HandlerResult<()> {
             )?;
             Ok(())
         }
+        MatchEvent::MatchStarted { .. } => Ok(()),
     }
 }
 
@@ -96,6 +98,7 @@ mod test {
             draws: 10,
             kills: 100,
             deaths: 0,
+            ..Default::default()
         }
     }
 }
@@ -0,0 +1,277 @@
+//! # Assembly Mechs: Match Runner
+//!
+//! Plays a match in-process, without NATS, a waSCC host or the engine provider. Each
+//! mech is represented by a `TurnHandler` that receives the same `TakeTurn` message
+//! the engine would send to its actor, and returns the commands for that turn.
+//!
+//! ```ignore
+//! let result = MatchRunner::new(parameters)
+//!     .with_mech(info("turret"), |tt: &TakeTurn| {
+//!         turret::handler(WasmdomeMechInstruments::new(tt.clone(), tt.actor.clone()))
+//!     })
+//!     .with_mech(info("sitting-duck"), |_tt: &TakeTurn| vec![])
+//!     .run()?;
+//! assert!(result.state.completed.is_some());
+//! ```
+
+extern crate wasmdome_domain as domain;
+extern crate wasmdome_protocol as protocol;
+
+use domain::eventsourcing::Result;
+use domain::{
+    commands::MechCommand,
+    events::GameEvent,
+    state::MatchState,
+    turns::{self, Entrant, MatchHost, TurnResponse},
+    MatchParameters,
+};
+use protocol::{commands::TakeTurn, MechInfo};
+use std::time::{Duration, Instant};
+
+/// Decides a mech's commands for a turn, given the match as the mech is allowed to see it
+pub trait TurnHandler {
+    fn take_turn(&mut self, turn: &TakeTurn) -> Vec<MechCommand>;
+}
+
+impl<F> TurnHandler for F
+where
+    F: FnMut(&TakeTurn) -> Vec<MechCommand>,
+{
+    fn take_turn(&mut self, turn: &TakeTurn) -> Vec<MechCommand> {
+        self(turn)
+    }
+}
+
+/// Everything that happened in a match that has been run to completion
+#[derive(Debug, Clone)]
+pub struct MatchResult {
+    /// Every event produced by the match, in the order they were applied
+    pub events: Vec<GameEvent>,
+    pub state: MatchState,
+}
+
+/// Runs a match between mechs whose turns are taken by in-process handlers
+pub struct MatchRunner {
+    parameters: MatchParameters,
+    mechs: Vec<(MechInfo, Box<dyn TurnHandler>)>,
+}
+
+impl MatchRunner {
+    /// Creates a runner for a match with the given parameters. The match's actors are the
+    /// mechs added to the runner, regardless of the actors listed in the parameters
+    pub fn new(parameters: MatchParameters) -> Self {
+        MatchRunner {
+            parameters,
+            mechs: Vec::new(),
+        }
+    }
+
+    /// Adds a mech to the match, whose turns will be taken by the given handler
+    pub fn with_mech(mut self, info: MechInfo, handler: impl TurnHandler + 'static) -> Self {
+        self.mechs.push((info, Box::new(handler)));
+        self
+    }
+
+    /// Plays the match until it is over or its last turn has been completed. The turns
+    /// are played in the same way as the engine plays them, so a match run with the same
+    /// seed and the same mechs produces the same events
+    pub fn run(self) -> Result<MatchResult> {
+        let mut mechs = self.mechs;
+        mechs.sort_by(|a, b| a.0.id.cmp(&b.0.id)); // spawn in the same order as the engine
+        let parameters = MatchParameters {
+            actors: mechs.iter().map(|(info, _)| info.id.to_string()).collect(),
+            ..self.parameters
+        };
+        let entrants: Vec<Entrant> = mechs.iter().map(|(info, _)| info.into()).collect();
+        let mut run = Run {
+            mechs,
+            events: Vec::new(),
+        };
+        let state = MatchState::new_with_parameters(parameters.clone());
+        let mut state = turns::spawn_mechs(&mut run, &state, &entrants)?;
+
+        let mut turn = 0;
+        while !turns::match_over(&state) && turn <= parameters.max_turns {
+            state = turns::play_turn(&mut run, &state, turn)?;
+            turn += 1;
+        }
+        Ok(MatchResult {
+            events: run.events,
+            state,
+        })
+    }
+}
+
+/// The mechs in a match being run, along with the events the match has produced
+struct Run {
+    mechs: Vec<(MechInfo, Box<dyn TurnHandler>)>,
+    events: Vec<GameEvent>,
+}
+
+impl MatchHost for Run {
+    /// Hands each mech its turn in turn. A handler can't be interrupted, so one that
+    /// takes longer than the match's turn timeout has timed out once it returns
+    fn request_turns(
+        &mut self,
+        state: &MatchState,
+        mechs: &[String],
+        turn: u32,
+    ) -> Vec<TurnResponse> {
+        let timeout = state.parameters.ruleset.turn_timeout_millis;
+        mechs
+            .iter()
+            .map(|actor| {
+                let tt = TakeTurn::new(state, actor, turn);
+                let handler = match self.mechs.iter_mut().find(|(info, _)| info.id == *actor) {
+                    Some((_, handler)) => handler,
+                    None => return TurnResponse::Failed,
+                };
+                let started = Instant::now();
+                let commands = handler.take_turn(&tt);
+                if timeout > 0 && started.elapsed() > Duration::from_millis(timeout) {
+                    TurnResponse::TimedOut
+                } else {
+                    TurnResponse::Taken(commands)
+                }
+            })
+            .collect()
+    }
+
+    fn event_applied(&mut self, _actor: &str, _turn: u32, evt: &GameEvent) {
+        self.events.push(evt.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use domain::events::EndCause;
+    use domain::{GridDirection, Ruleset, TurnMode, TurnOrder};
+    use std::collections::HashMap;
+
+    fn gen_parameters(turn_mode: TurnMode) -> MatchParameters {
+        MatchParameters::new(
+            "test_match".to_string(),
+            20,
+            20,
+            5,
+            4,
+            vec![],
+            Ruleset::default(),
+            vec![],
+            turn_mode,
+            TurnOrder::Fixed,
+            42,
+            HashMap::new(),
+        )
+    }
+
+    fn info(id: &str) -> MechInfo {
+        MechInfo {
+            name: id.to_string(),
+            avatar: "none".to_string(),
+            team: "earth".to_string(),
+            id: id.to_string(),
+        }
+    }
+
+    fn wanderer(tt: &TakeTurn) -> Vec<MechCommand> {
+        vec![MechCommand::Move {
+            turn: tt.turn,
+            mech: tt.actor.to_string(),
+            direction: GridDirection::East,
+        }]
+    }
+
+    #[test]
+    fn idle_match_runs_to_max_turns() {
+        for mode in &[TurnMode::Sequential, TurnMode::Simultaneous] {
+            let result = MatchRunner::new(gen_parameters(*mode))
+                .with_mech(info("al"), |_tt: &TakeTurn| vec![])
+                .with_mech(info("bob"), wanderer)
+                .run()
+                .unwrap();
+            let mut survivors = vec!["al".to_string(), "bob".to_string()];
+            survivors.sort();
+            match result.state.completed {
+                Some(EndCause::MaxTurnsCompleted { survivors: mut s }) => {
+                    s.sort();
+                    assert_eq!(s, survivors);
+                }
+                other => panic!("unexpected end of match: {:?}", other),
+            }
+            assert!(result.events.iter().any(
+                |evt| matches!(evt, GameEvent::PositionUpdated { mech, .. } if mech == "bob")
+            ));
+        }
+    }
+
+    #[test]
+    fn defenses_drop_as_the_next_turn_begins() {
+        for mode in &[TurnMode::Sequential, TurnMode::Simultaneous] {
+            let result = MatchRunner::new(gen_parameters(*mode))
+                .with_mech(info("al"), |tt: &TakeTurn| match tt.turn {
+                    0 => vec![MechCommand::RaiseShield {
+                        turn: 0,
+                        mech: tt.actor.to_string(),
+                    }],
+                    _ => vec![],
+                })
+                .run()
+                .unwrap();
+            let lowered = result
+                .events
+                .iter()
+                .position(|evt| matches!(evt, GameEvent::DefensesLowered { .. }))
+                .unwrap();
+            let turn_ended = result
+                .events
+                .iter()
+                .position(|evt| matches!(evt, GameEvent::MatchTurnCompleted { new_turn: 1 }))
+                .unwrap();
+            assert!(lowered > turn_ended);
+        }
+    }
+
+    #[test]
+    fn slow_mechs_time_out() {
+        let parameters = MatchParameters {
+            ruleset: Ruleset {
+                turn_timeout_millis: 5,
+                max_timeouts: 0,
+                ..Default::default()
+            },
+            max_turns: 2,
+            ..gen_parameters(TurnMode::Sequential)
+        };
+        let result = MatchRunner::new(parameters)
+            .with_mech(info("al"), wanderer)
+            .with_mech(info("bob"), |tt: &TakeTurn| {
+                std::thread::sleep(Duration::from_millis(20));
+                wanderer(tt)
+            })
+            .run()
+            .unwrap();
+        assert!(result
+            .events
+            .iter()
+            .any(|evt| matches!(evt, GameEvent::TurnTimedOut { mech, .. } if mech == "bob")));
+        assert!(!result
+            .events
+            .iter()
+            .any(|evt| matches!(evt, GameEvent::PositionUpdated { mech, .. } if mech == "bob")));
+    }
+
+    #[test]
+    fn same_seed_same_events() {
+        let run = || {
+            MatchRunner::new(gen_parameters(TurnMode::Sequential))
+                .with_mech(info("bob"), wanderer)
+                .with_mech(info("al"), wanderer)
+                .run()
+                .unwrap()
+        };
+        let (a, b) = (run(), run());
+        assert_eq!(format!("{:?}", a.events), format!("{:?}", b.events));
+    }
+}
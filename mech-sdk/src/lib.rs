@@ -30,16 +30,23 @@
 //!| [fire_primary](trait.MechInstruments.html#tymethod.fire_primary)| 2 | Fires the mech's primary weapon in a given direction. Primary weapons fire a single small projectile that will damage the first thing it encounters. Primary weapon range is available via sensor interrogation. |
 //!| [fire_secondary](trait.MechInstruments.html#tymethod.fire_secondary)| 4 | Fires the mech's secondary weapon in a given direction. Secondary weapons fire an explosive projectile that damages the first thing it encounters, as well as producing splash damage that radiates out from the point of impact. Secondary weapon range is available via sensor interrogation.  |
 //!| [radar_scan](trait.MechInstruments.html#tymethod.radar_scan) | 1 | Performs a full radar scan of the mech's surroundings, reporting on detected enemies and obstacles. The mech will receive the results of the scan at the beginning of the next turn.|
+//!| [request_radar_toward](trait.MechInstruments.html#tymethod.request_radar_toward) | 1 | Performs a radar scan aimed in a given direction. The direction only matters in matches using a cone-shaped [radar pattern](trait.MechInstruments.html#tymethod.radar_pattern).|
 //!
 //!The default, unaffected power of a mech is **4** units, meaning that within a single turn a mech may fire its secondary weapon once,
 //! move 4 times, or perform some other combination of actions. Accessing sensor values does not cost you anything.
 //!
+//! The costs, ranges, and damage listed here are those of the standard ruleset. Individual matches may be started with a
+//! different ruleset, so your mech should rely on its sensors (e.g. [power](trait.MechInstruments.html#tymethod.power) and
+//! [primary_range](trait.MechInstruments.html#tymethod.primary_range)) rather than hard-coded values.
+//!
 //! ## Warnings
 //! Take care not to exceed the maximum number of action points consumed in a given turn. At best, commands exceeding your [power](trait.MechInstruments.html#tymethod.power)
 //! will fail, at worst (depending on the match rules) your mech might be penalized for the attempt
 //!
 //! Collision damage is real, and your mech's hull will lose structural integrity when colliding with other mechs and with walls
 //!
+//! Radar requires line of sight. The first mech or solid obstacle along a line hides anything standing behind it
+//!
 //! # Example
 //! ```
 //! extern crate wasmdome_mech_sdk as mech;
@@ -59,19 +66,34 @@
 //! }
 //!
 //! ```
+//!
+//! ## Testing
+//! The [testing](testing/index.html) module lets you unit-test your handler by describing the situation your mech is in
+//! and checking the commands it returns, without starting an arena. It is only built with the `testing` feature, so turn
+//! the feature on in your mech's `[dev-dependencies]` to keep it out of the module you deploy:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! wasmdome-mech-sdk = { version = "0.0.8", features = ["testing"] }
+//! ```
 
 pub extern crate wascc_actor;
 pub extern crate wasmdome_protocol as protocol;
 
 use wasmdome_domain as domain;
 
-use domain::state::MechState;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
 pub use domain::{
-    commands::MechCommand, GameBoard, GridDirection, Point, RegisterOperation, RegisterValue, EAX,
-    EBX, ECX,
+    commands::{CommandRejection, MechCommand},
+    numeric_register, text_register, GameBoard, GridDirection, Obstacle, Penalty, PickupKind,
+    PingKind, Point, RadarPattern, RegisterFile, RegisterOperation, RegisterValue, TerrainType,
+    WeaponType, EAX, EBX, ECX, MAX_GENERAL_REGISTERS, MEM,
 };
+use domain::{state::MechState, SeededRng};
 
-use wascc_actor::prelude::*;
+use std::cell::RefCell;
 
 /// Declares the function to be used as the mech's turn handler
 #[macro_export]
@@ -127,14 +149,33 @@ pub trait MechInstruments {
     fn primary_range(&self) -> u32;
     /// Returns the range (in whole grid units) of the secondary weapon, which defaults to **6** but your code should use this if you need to perform calculations based on range
     fn secondary_range(&self) -> u32;
-    /// Accesses the last radar scan (if any) performed by your mech. If on turn **x** your mech has a radar request in the command list, then on turn **x+1** that scan's results will be available
+    /// Accesses the last radar scan (if any) performed by your mech. If on turn **x** your mech has a radar request in the command list, then on turn **x+1** that scan's results will be available.
+    /// Radar is the only way your mech can learn about other mechs and pickups in the arena
     fn last_radar_scan(&self) -> Option<Vec<RadarPing>>;
     /// A handy function that performs the Euclidean calculation for you in order to determine the direction between your mech and a target point
     fn direction_to(&self, target: &Point) -> GridDirection;
-    /// Generate a random number between the min and max values (inclusive)
+    /// Generate a random number between the min and max values (inclusive). The numbers are drawn from the match's seed, so a mech replaying
+    /// a match with the same seed will receive the same numbers on every turn
     fn random_number(&self, min: u32, max: u32) -> u32;
     /// Obtains the dimensions of the arena in which the mech resides
     fn world_size(&self) -> GameBoard;
+    /// Plots the shortest series of moves from your mech's position to the target that steers clear of the arena's known terrain.
+    /// The path is empty if the target can't be reached. Other mechs aren't taken into account, so watch out for collisions
+    fn path_to(&self, target: &Point) -> Vec<GridDirection>;
+    /// Returns the shape of the area covered by a radar scan in this match. Defaults to a star of eight rays
+    fn radar_pattern(&self) -> RadarPattern;
+    /// Lists the penalties your mech received during the previous turn for issuing commands that exceeded its remaining action points
+    fn penalties(&self) -> Vec<Penalty>;
+    /// Lists the commands from your mech's previous turn that the arena refused to carry out, along with the reason for each
+    fn rejections(&self) -> Vec<CommandRejection>;
+    /// Returns the rounds left for the given weapon, or `None` if the match places no limit on its ammunition
+    fn ammo(&self, weapon: WeaponType) -> Option<u32>;
+    /// Returns the number of turns until the given weapon can be fired again. A weapon with a cooldown of **0** is ready to fire
+    fn cooldown(&self, weapon: WeaponType) -> u32;
+    /// Returns the direction your mech is facing. This only matters in matches where mechs have a firing arc
+    fn facing(&self) -> GridDirection;
+    /// Returns the width (in degrees) of the arc centered on your mech's facing within which it can fire and scan, or `None` if mechs can fire in every direction
+    fn firing_arc(&self) -> Option<u32>;
 
     //- Registers
 
@@ -144,23 +185,56 @@ pub trait MechInstruments {
     fn register_dec(&self, reg: u32, val: u64) -> MechCommand;
     /// Sets the value in the given register. This will overwrite any previously existing value
     fn register_set(&self, reg: u32, val: RegisterValue) -> MechCommand;
+    /// Multiplies the value in the given register, saturating at the largest possible number
+    fn register_mul(&self, reg: u32, val: u64) -> MechCommand;
+    /// Replaces the value in the given register with its remainder when divided by `val`
+    fn register_mod(&self, reg: u32, val: u64) -> MechCommand;
+    /// Sets the value in the given register only if it currently holds the expected value
+    fn register_cas(&self, reg: u32, expected: RegisterValue, new: RegisterValue) -> MechCommand;
+    /// Copies the value of the `from` register into the given register. Both registers must hold the same kind of value
+    fn register_copy(&self, reg: u32, from: u32) -> MechCommand;
+    /// Exchanges the values of two registers that hold the same kind of value
+    fn register_swap(&self, reg: u32, with: u32) -> MechCommand;
+    /// Empties the given register
+    fn register_clear(&self, reg: u32) -> MechCommand;
     /// Queries the value (if any) stored in the given register
     fn register_get(&self, reg: u32) -> Option<&RegisterValue>;
+    /// Queries the number stored in the given register, if it holds one
+    fn register_number(&self, reg: u32) -> Option<u64>;
+    /// Queries the text stored in the given register, if it holds any
+    fn register_text(&self, reg: u32) -> Option<&str>;
+    /// Queries the bytes stored in your mech's scratch memory (the `MEM` register)
+    fn memory(&self) -> Option<&[u8]>;
+    /// Overwrites your mech's scratch memory. The command is rejected if the bytes exceed the match's memory quota
+    fn memory_set(&self, bytes: Vec<u8>) -> MechCommand;
+    /// Describes the registers available to your mech in this match
+    fn register_file(&self) -> &RegisterFile;
 
     //- Generate commands
 
     /// Generates a radar request command to be processed by the game engine at the end of this turn
     fn request_radar(&self) -> MechCommand;
+    /// Generates a radar request aimed in the given direction. Cone-shaped radar scans without a direction face north
+    fn request_radar_toward(&self, dir: GridDirection) -> MechCommand;
     /// Generates a request to fire the primary weapon
     fn fire_primary(&self, dir: GridDirection) -> MechCommand;
     /// Generates a request to fire the secondary weapon
     fn fire_secondary(&self, dir: GridDirection) -> MechCommand;
     /// Generates a request to move the mech
     fn move_mech(&self, dir: GridDirection) -> MechCommand;
+    /// Generates a request to move the mech along the shortest path to the target, taking as many steps as its remaining action points allow
+    fn move_to(&self, target: &Point) -> MechCommand;
+    /// Generates a request to turn the mech to face the given direction
+    fn rotate(&self, dir: GridDirection) -> MechCommand;
+    /// Generates a request to raise the mech's shield, which absorbs part of the damage it takes until its next turn
+    fn raise_shield(&self) -> MechCommand;
+    /// Generates a request to brace the mech, protecting it from collision damage until its next turn
+    fn brace(&self) -> MechCommand;
 }
 
 /// A single result from a radar scan. When a mech queries for the last radar scan and
 /// one is available, those results will be a vector of these radar pings
+#[derive(Debug, Clone)]
 pub struct RadarPing {
     /// A unique identifier for this target. This is an opaque string and you should assign no internal meaning to it other than to distinguish one target from another
     pub id: String,
@@ -170,18 +244,25 @@ pub struct RadarPing {
     pub location: Point,
     /// A rounded, whole number indicating the distance to the discovered target
     pub distance: usize,
+    /// Indicates whether the target is another mech, a piece of terrain such as a wall, cover, or a pit, or a pickup
+    pub kind: PingKind,
 }
 
 #[doc(hidden)]
 pub struct WasmdomeMechInstruments {
     actor: String,
     turn: protocol::commands::TakeTurn,
+    rng: RefCell<SeededRng>,
 }
 
 #[doc(hidden)]
 impl WasmdomeMechInstruments {
     pub fn new(turn: protocol::commands::TakeTurn, actor: String) -> Self {
-        WasmdomeMechInstruments { turn, actor }
+        WasmdomeMechInstruments {
+            rng: RefCell::new(SeededRng::new(turn.rng_seed)),
+            turn,
+            actor,
+        }
     }
 }
 
@@ -191,6 +272,15 @@ impl WasmdomeMechInstruments {
         &self.turn.state.mechs[&self.actor]
     }
 
+    fn register_update(&self, reg: u32, op: RegisterOperation) -> MechCommand {
+        MechCommand::RegisterUpdate {
+            mech: self.current_mech().id.to_string(),
+            reg,
+            op,
+            turn: self.turn.turn,
+        }
+    }
+
     #[allow(dead_code)]
     pub fn is_alive(&self) -> bool {
         self.current_mech().alive
@@ -211,11 +301,11 @@ impl MechInstruments for WasmdomeMechInstruments {
     }
 
     fn primary_range(&self) -> u32 {
-        domain::state::PRIMARY_RANGE as u32
+        self.turn.state.parameters.ruleset.primary_range as u32
     }
 
     fn secondary_range(&self) -> u32 {
-        domain::state::SECONDARY_RANGE as u32
+        self.turn.state.parameters.ruleset.secondary_range as u32
     }
 
     fn direction_to(&self, target: &Point) -> GridDirection {
@@ -223,19 +313,68 @@ impl MechInstruments for WasmdomeMechInstruments {
     }
 
     fn random_number(&self, min: u32, max: u32) -> u32 {
-        match extras::default().get_random(min, max) {
-            Ok(r) => r,
-            Err(_) => 0,
-        }
+        self.rng.borrow_mut().between(min, max)
     }
 
     fn world_size(&self) -> GameBoard {
         GameBoard {
             height: self.turn.state.parameters.height,
             width: self.turn.state.parameters.width,
+            ..Default::default()
         }
     }
 
+    fn path_to(&self, target: &Point) -> Vec<GridDirection> {
+        self.turn
+            .state
+            .game_board
+            .path(&self.position(), target)
+            .unwrap_or_default()
+    }
+
+    fn radar_pattern(&self) -> RadarPattern {
+        self.turn.state.parameters.ruleset.radar_pattern.clone()
+    }
+
+    fn penalties(&self) -> Vec<Penalty> {
+        self.current_mech()
+            .penalties
+            .iter()
+            .filter(|(turn, _p)| *turn < self.turn.state.turn_status.current)
+            .map(|(_t, p)| p.clone())
+            .collect()
+    }
+
+    fn rejections(&self) -> Vec<CommandRejection> {
+        self.current_mech()
+            .rejections
+            .iter()
+            .filter(|r| r.turn < self.turn.state.turn_status.current)
+            .cloned()
+            .collect()
+    }
+
+    fn ammo(&self, weapon: WeaponType) -> Option<u32> {
+        self.current_mech().ammo.get(&weapon).cloned()
+    }
+
+    fn cooldown(&self, weapon: WeaponType) -> u32 {
+        self.current_mech()
+            .cooldowns
+            .get(&weapon)
+            .map_or(0, |ready_on| {
+                ready_on.saturating_sub(self.turn.state.turn_status.current)
+            })
+    }
+
+    fn facing(&self) -> GridDirection {
+        self.current_mech().facing
+    }
+
+    fn firing_arc(&self) -> Option<u32> {
+        self.turn.state.parameters.ruleset.facing_arc
+    }
+
     //- Registers
     fn register_acc(&self, reg: u32, val: u64) -> MechCommand {
         MechCommand::RegisterUpdate {
@@ -264,16 +403,78 @@ impl MechInstruments for WasmdomeMechInstruments {
         }
     }
 
+    fn register_mul(&self, reg: u32, val: u64) -> MechCommand {
+        self.register_update(reg, RegisterOperation::Multiply(val))
+    }
+
+    fn register_mod(&self, reg: u32, val: u64) -> MechCommand {
+        self.register_update(reg, RegisterOperation::Modulo(val))
+    }
+
+    fn register_cas(&self, reg: u32, expected: RegisterValue, new: RegisterValue) -> MechCommand {
+        self.register_update(reg, RegisterOperation::CompareAndSet { expected, new })
+    }
+
+    fn register_copy(&self, reg: u32, from: u32) -> MechCommand {
+        self.register_update(reg, RegisterOperation::Copy { from })
+    }
+
+    fn register_swap(&self, reg: u32, with: u32) -> MechCommand {
+        self.register_update(reg, RegisterOperation::Swap { with })
+    }
+
+    fn register_clear(&self, reg: u32) -> MechCommand {
+        self.register_update(reg, RegisterOperation::Clear)
+    }
+
     fn register_get(&self, reg: u32) -> Option<&RegisterValue> {
         self.current_mech().registers.get(&reg)
     }
 
+    fn register_number(&self, reg: u32) -> Option<u64> {
+        match self.register_get(reg) {
+            Some(RegisterValue::Number(n)) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn register_text(&self, reg: u32) -> Option<&str> {
+        match self.register_get(reg) {
+            Some(RegisterValue::Text(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn memory(&self) -> Option<&[u8]> {
+        match self.register_get(MEM) {
+            Some(RegisterValue::Bytes(b)) => Some(b),
+            _ => None,
+        }
+    }
+
+    fn memory_set(&self, bytes: Vec<u8>) -> MechCommand {
+        self.register_set(MEM, RegisterValue::Bytes(bytes))
+    }
+
+    fn register_file(&self) -> &RegisterFile {
+        &self.turn.state.parameters.ruleset.registers
+    }
+
     //- Generate commands
 
     fn request_radar(&self) -> MechCommand {
         MechCommand::RequestRadarScan {
             turn: self.turn.turn,
             mech: self.actor.to_string(),
+            direction: None,
+        }
+    }
+
+    fn request_radar_toward(&self, dir: GridDirection) -> MechCommand {
+        MechCommand::RequestRadarScan {
+            turn: self.turn.turn,
+            mech: self.actor.to_string(),
+            direction: Some(dir),
         }
     }
 
@@ -301,6 +502,36 @@ impl MechInstruments for WasmdomeMechInstruments {
         }
     }
 
+    fn move_to(&self, target: &Point) -> MechCommand {
+        MechCommand::MoveTo {
+            turn: self.turn.turn,
+            mech: self.actor.to_string(),
+            target: target.clone(),
+        }
+    }
+
+    fn rotate(&self, dir: GridDirection) -> MechCommand {
+        MechCommand::Rotate {
+            turn: self.turn.turn,
+            mech: self.actor.to_string(),
+            direction: dir,
+        }
+    }
+
+    fn raise_shield(&self) -> MechCommand {
+        MechCommand::RaiseShield {
+            turn: self.turn.turn,
+            mech: self.actor.to_string(),
+        }
+    }
+
+    fn brace(&self) -> MechCommand {
+        MechCommand::Brace {
+            turn: self.turn.turn,
+            mech: self.actor.to_string(),
+        }
+    }
+
     fn last_radar_scan(&self) -> Option<Vec<RadarPing>> {
         self.turn.state.radar_pings.get(&self.actor).map(|pings| {
             pings
@@ -310,6 +541,7 @@ impl MechInstruments for WasmdomeMechInstruments {
                     distance: p.distance,
                     foe: p.foe,
                     location: p.location.clone(),
+                    kind: p.kind.clone(),
                 })
                 .collect()
         })
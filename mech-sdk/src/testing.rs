@@ -0,0 +1,620 @@
+//! # Testing Your Mech
+//!
+//! Tools for unit-testing a mech's `handler` without an arena. A [Scenario](struct.Scenario.html)
+//! describes the situation your mech finds itself in at the start of a turn, and hands your
+//! handler the same instruments it would receive in a real match, fed from that situation
+//! instead of a live one.
+//!
+//! ```
+//! extern crate wasmdome_mech_sdk as mech;
+//!
+//! use mech::testing::Scenario;
+//! use mech::*;
+//!
+//! fn handler(mech: impl MechInstruments) -> Vec<MechCommand> {
+//!     match mech.last_radar_scan().and_then(|scan| scan.into_iter().find(|p| p.foe)) {
+//!         Some(foe) => vec![mech.fire_primary(mech.direction_to(&foe.location))],
+//!         None => vec![mech.request_radar()],
+//!     }
+//! }
+//!
+//! let scenario = Scenario::new()
+//!     .with_position(Point::new(5, 5))
+//!     .with_enemy("boylur-plait", Point::new(5, 8));
+//! let commands = handler(scenario.mech());
+//! scenario.assert_within_power(&commands);
+//! scenario.assert_fires_toward_nearest_foe(&commands);
+//! ```
+//!
+//! When a reading is awkward to arrange through a match, such as a particular random number
+//! or a weapon still cooling down, take a [MockInstruments](struct.MockInstruments.html) from
+//! the scenario and set the reading directly:
+//!
+//! ```
+//! # extern crate wasmdome_mech_sdk as mech;
+//! # use mech::testing::Scenario;
+//! # use mech::*;
+//! let mut mech = Scenario::new().mock();
+//! mech.cooldowns.insert(WeaponType::Primary, 2);
+//! assert_eq!(mech.cooldown(WeaponType::Primary), 2);
+//! ```
+
+use crate::domain::{
+    commands::MechCommand,
+    events::GameEvent,
+    eventsourcing::Aggregate,
+    state::{Match, MatchState},
+    GridDirection, MatchParameters, Obstacle, PickupKind, PingKind, Point, RadarPing,
+    RegisterValue, Ruleset,
+};
+use crate::protocol::commands::TakeTurn;
+use crate::{
+    CommandRejection, GameBoard, MechInstruments, Penalty, RadarPattern, RegisterFile,
+    RegisterOperation, WasmdomeMechInstruments, WeaponType, MEM,
+};
+use std::collections::HashMap;
+
+const MECH_ID: &str = "test-mech";
+const MECH_TEAM: &str = "earth";
+
+/// The situation a mech finds itself in at the start of a turn
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    parameters: MatchParameters,
+    turn: u32,
+    position: Point,
+    health: Option<u32>,
+    facing: GridDirection,
+    registers: Vec<(u32, RegisterValue)>,
+    enemies: Vec<(String, Point)>,
+    radar: Option<Vec<RadarPing>>,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Scenario {
+            parameters: MatchParameters {
+                match_id: "test-match".to_string(),
+                width: 20,
+                height: 20,
+                actors: vec![MECH_ID.to_string()],
+                max_turns: 100,
+                aps_per_turn: 4,
+                ..Default::default()
+            },
+            turn: 0,
+            position: Point::new(10, 10),
+            health: None,
+            facing: GridDirection::North,
+            registers: Vec::new(),
+            enemies: Vec::new(),
+            radar: None,
+        }
+    }
+}
+
+impl Scenario {
+    /// Starts a scenario on the first turn of a 20x20 match played with the standard rules,
+    /// with your mech standing in the middle of the board
+    pub fn new() -> Self {
+        Scenario::default()
+    }
+
+    pub fn with_board(mut self, width: u32, height: u32) -> Self {
+        self.parameters.width = width;
+        self.parameters.height = height;
+        self
+    }
+
+    pub fn with_terrain(mut self, obstacle: Obstacle) -> Self {
+        self.parameters.terrain.push(obstacle);
+        self
+    }
+
+    pub fn with_ruleset(mut self, ruleset: Ruleset) -> Self {
+        self.parameters.ruleset = ruleset;
+        self
+    }
+
+    /// Sets the number of action points your mech gets per turn
+    pub fn with_power(mut self, aps_per_turn: u32) -> Self {
+        self.parameters.aps_per_turn = aps_per_turn;
+        self
+    }
+
+    pub fn with_turn(mut self, turn: u32) -> Self {
+        self.turn = turn;
+        self
+    }
+
+    pub fn with_position(mut self, position: Point) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_health(mut self, health: u32) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    pub fn with_facing(mut self, facing: GridDirection) -> Self {
+        self.facing = facing;
+        self
+    }
+
+    pub fn with_register(mut self, reg: u32, val: RegisterValue) -> Self {
+        self.registers.push((reg, val));
+        self
+    }
+
+    /// Adds an enemy mech to the scenario and reports it in your mech's last radar scan
+    pub fn with_enemy(mut self, id: &str, position: Point) -> Self {
+        let ping = RadarPing {
+            name: id.to_string(),
+            avatar: "none".to_string(),
+            foe: true,
+            distance: 0, // measured from wherever your mech ends up
+            location: position.clone(),
+            kind: PingKind::Mech,
+        };
+        self.enemies.push((id.to_string(), position));
+        self.with_radar_ping(ping)
+    }
+
+    /// Reports a pickup in your mech's last radar scan
+    pub fn with_pickup(self, position: Point, kind: PickupKind) -> Self {
+        let ping = RadarPing {
+            name: "pickup".to_string(),
+            avatar: "none".to_string(),
+            foe: false,
+            distance: 0,
+            location: position,
+            kind: PingKind::Pickup(kind),
+        };
+        self.with_radar_ping(ping)
+    }
+
+    /// Adds a ping to your mech's last radar scan. The ping's distance is measured from your mech's position
+    pub fn with_radar_ping(mut self, ping: RadarPing) -> Self {
+        self.radar.get_or_insert_with(Vec::new).push(ping);
+        self
+    }
+
+    /// Produces the instruments your handler receives for this scenario
+    pub fn mech(&self) -> WasmdomeMechInstruments {
+        WasmdomeMechInstruments::new(self.take_turn(), MECH_ID.to_string())
+    }
+
+    /// Produces a mock instruments panel whose readings start out as this scenario's
+    pub fn mock(&self) -> MockInstruments {
+        let mech = self.mech();
+        let weapons = [WeaponType::Primary, WeaponType::Secondary];
+        MockInstruments {
+            turn: self.turn,
+            position: mech.position(),
+            hull_integrity: mech.hull_integrity(),
+            power: mech.power(),
+            primary_range: mech.primary_range(),
+            secondary_range: mech.secondary_range(),
+            radar: mech.last_radar_scan(),
+            random: 0,
+            world_size: GameBoard {
+                terrain: self.parameters.terrain.clone(),
+                ..mech.world_size()
+            },
+            radar_pattern: mech.radar_pattern(),
+            penalties: mech.penalties(),
+            rejections: mech.rejections(),
+            ammo: weapons
+                .iter()
+                .filter_map(|w| mech.ammo(*w).map(|rounds| (*w, rounds)))
+                .collect(),
+            cooldowns: HashMap::new(),
+            facing: mech.facing(),
+            firing_arc: mech.firing_arc(),
+            registers: self.registers.iter().cloned().collect(),
+            register_file: mech.register_file().clone(),
+        }
+    }
+
+    /// Produces the message the arena would send your mech for this scenario
+    pub fn take_turn(&self) -> TakeTurn {
+        let spawned = GameEvent::MechSpawned {
+            mech: MECH_ID.to_string(),
+            position: self.position.clone(),
+            team: MECH_TEAM.to_string(),
+            avatar: "none".to_string(),
+            name: MECH_ID.to_string(),
+        };
+        let mut state = Match::apply_event(
+            &MatchState::new_with_parameters(self.parameters.clone()),
+            &spawned,
+        )
+        .unwrap();
+        state.turn_status.current = self.turn;
+        if let Some(mech) = state.mechs.get_mut(MECH_ID) {
+            mech.health = self.health.unwrap_or(mech.health);
+            mech.facing = self.facing;
+            mech.remaining_aps = self.parameters.aps_per_turn;
+            mech.registers.extend(self.registers.iter().cloned());
+        }
+        if let Some(pings) = &self.radar {
+            let pings = pings
+                .iter()
+                .map(|p| RadarPing {
+                    distance: self.position.distance_to(&p.location) as usize,
+                    ..p.clone()
+                })
+                .collect();
+            state.radar_pings.insert(MECH_ID.to_string(), pings);
+        }
+        TakeTurn::new(&state, MECH_ID, self.turn)
+    }
+
+    /// The total action points the given commands cost under this scenario's rules
+    pub fn cost(&self, commands: &[MechCommand]) -> u32 {
+        commands
+            .iter()
+            .map(|cmd| cmd.action_points(&self.parameters.ruleset))
+            .sum()
+    }
+
+    /// Asserts that the commands don't cost more action points than your mech has
+    pub fn assert_within_power(&self, commands: &[MechCommand]) {
+        let cost = self.cost(commands);
+        assert!(
+            cost <= self.parameters.aps_per_turn,
+            "commands cost {} action points but the mech only has {}: {:?}",
+            cost,
+            self.parameters.aps_per_turn,
+            commands
+        );
+    }
+
+    /// Asserts that at least one of the commands fires a weapon in the direction of the
+    /// nearest enemy placed in the scenario
+    pub fn assert_fires_toward_nearest_foe(&self, commands: &[MechCommand]) {
+        let nearest = self
+            .enemies
+            .iter()
+            .min_by_key(|(_id, p)| self.position.distance_to(p))
+            .expect("the scenario has no enemies");
+        let toward = self.position.bearing(&nearest.1);
+        assert!(
+            commands.iter().any(|cmd| match cmd {
+                MechCommand::FirePrimary { direction, .. }
+                | MechCommand::FireSecondary { direction, .. } => *direction == toward,
+                _ => false,
+            }),
+            "expected a weapon fired {:?} toward {} at {:?}: {:?}",
+            toward,
+            nearest.0,
+            nearest.1,
+            commands
+        );
+    }
+}
+
+/// An instruments panel whose readings are plain fields, set directly by the test rather
+/// than taken from a match. Commands are generated just as a real panel generates them
+#[derive(Debug, Clone)]
+pub struct MockInstruments {
+    /// The turn stamped on the commands the panel generates
+    pub turn: u32,
+    pub position: Point,
+    pub hull_integrity: u32,
+    pub power: u32,
+    pub primary_range: u32,
+    pub secondary_range: u32,
+    pub radar: Option<Vec<crate::RadarPing>>,
+    /// Returned by every call to `random_number`, kept within the range asked for
+    pub random: u32,
+    /// The board, including any terrain that `path_to` has to steer around
+    pub world_size: GameBoard,
+    pub radar_pattern: RadarPattern,
+    pub penalties: Vec<Penalty>,
+    pub rejections: Vec<CommandRejection>,
+    /// Rounds left per weapon. Weapons left out have unlimited ammunition
+    pub ammo: HashMap<WeaponType, u32>,
+    /// Turns until each weapon can fire again. Weapons left out are ready
+    pub cooldowns: HashMap<WeaponType, u32>,
+    pub facing: GridDirection,
+    pub firing_arc: Option<u32>,
+    pub registers: HashMap<u32, RegisterValue>,
+    pub register_file: RegisterFile,
+}
+
+impl MockInstruments {
+    fn register_update(&self, reg: u32, op: RegisterOperation) -> MechCommand {
+        MechCommand::RegisterUpdate {
+            mech: MECH_ID.to_string(),
+            reg,
+            op,
+            turn: self.turn,
+        }
+    }
+}
+
+impl MechInstruments for MockInstruments {
+    fn position(&self) -> Point {
+        self.position.clone()
+    }
+
+    fn hull_integrity(&self) -> u32 {
+        self.hull_integrity
+    }
+
+    fn power(&self) -> u32 {
+        self.power
+    }
+
+    fn primary_range(&self) -> u32 {
+        self.primary_range
+    }
+
+    fn secondary_range(&self) -> u32 {
+        self.secondary_range
+    }
+
+    fn last_radar_scan(&self) -> Option<Vec<crate::RadarPing>> {
+        self.radar.clone()
+    }
+
+    fn direction_to(&self, target: &Point) -> GridDirection {
+        self.position.bearing(target)
+    }
+
+    fn random_number(&self, min: u32, max: u32) -> u32 {
+        self.random.max(min).min(max)
+    }
+
+    fn world_size(&self) -> GameBoard {
+        self.world_size.clone()
+    }
+
+    fn path_to(&self, target: &Point) -> Vec<GridDirection> {
+        self.world_size
+            .path(&self.position, target)
+            .unwrap_or_default()
+    }
+
+    fn radar_pattern(&self) -> RadarPattern {
+        self.radar_pattern.clone()
+    }
+
+    fn penalties(&self) -> Vec<Penalty> {
+        self.penalties.clone()
+    }
+
+    fn rejections(&self) -> Vec<CommandRejection> {
+        self.rejections.clone()
+    }
+
+    fn ammo(&self, weapon: WeaponType) -> Option<u32> {
+        self.ammo.get(&weapon).cloned()
+    }
+
+    fn cooldown(&self, weapon: WeaponType) -> u32 {
+        self.cooldowns.get(&weapon).cloned().unwrap_or(0)
+    }
+
+    fn facing(&self) -> GridDirection {
+        self.facing
+    }
+
+    fn firing_arc(&self) -> Option<u32> {
+        self.firing_arc
+    }
+
+    fn register_acc(&self, reg: u32, val: u64) -> MechCommand {
+        self.register_update(reg, RegisterOperation::Accumulate(val))
+    }
+
+    fn register_dec(&self, reg: u32, val: u64) -> MechCommand {
+        self.register_update(reg, RegisterOperation::Decrement(val))
+    }
+
+    fn register_set(&self, reg: u32, val: RegisterValue) -> MechCommand {
+        self.register_update(reg, RegisterOperation::Set(val))
+    }
+
+    fn register_mul(&self, reg: u32, val: u64) -> MechCommand {
+        self.register_update(reg, RegisterOperation::Multiply(val))
+    }
+
+    fn register_mod(&self, reg: u32, val: u64) -> MechCommand {
+        self.register_update(reg, RegisterOperation::Modulo(val))
+    }
+
+    fn register_cas(&self, reg: u32, expected: RegisterValue, new: RegisterValue) -> MechCommand {
+        self.register_update(reg, RegisterOperation::CompareAndSet { expected, new })
+    }
+
+    fn register_copy(&self, reg: u32, from: u32) -> MechCommand {
+        self.register_update(reg, RegisterOperation::Copy { from })
+    }
+
+    fn register_swap(&self, reg: u32, with: u32) -> MechCommand {
+        self.register_update(reg, RegisterOperation::Swap { with })
+    }
+
+    fn register_clear(&self, reg: u32) -> MechCommand {
+        self.register_update(reg, RegisterOperation::Clear)
+    }
+
+    fn register_get(&self, reg: u32) -> Option<&RegisterValue> {
+        self.registers.get(&reg)
+    }
+
+    fn register_number(&self, reg: u32) -> Option<u64> {
+        match self.register_get(reg) {
+            Some(RegisterValue::Number(n)) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn register_text(&self, reg: u32) -> Option<&str> {
+        match self.register_get(reg) {
+            Some(RegisterValue::Text(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn memory(&self) -> Option<&[u8]> {
+        match self.register_get(MEM) {
+            Some(RegisterValue::Bytes(b)) => Some(b),
+            _ => None,
+        }
+    }
+
+    fn memory_set(&self, bytes: Vec<u8>) -> MechCommand {
+        self.register_set(MEM, RegisterValue::Bytes(bytes))
+    }
+
+    fn register_file(&self) -> &RegisterFile {
+        &self.register_file
+    }
+
+    fn request_radar(&self) -> MechCommand {
+        MechCommand::RequestRadarScan {
+            turn: self.turn,
+            mech: MECH_ID.to_string(),
+            direction: None,
+        }
+    }
+
+    fn request_radar_toward(&self, dir: GridDirection) -> MechCommand {
+        MechCommand::RequestRadarScan {
+            turn: self.turn,
+            mech: MECH_ID.to_string(),
+            direction: Some(dir),
+        }
+    }
+
+    fn fire_primary(&self, dir: GridDirection) -> MechCommand {
+        MechCommand::FirePrimary {
+            turn: self.turn,
+            mech: MECH_ID.to_string(),
+            direction: dir,
+        }
+    }
+
+    fn fire_secondary(&self, dir: GridDirection) -> MechCommand {
+        MechCommand::FireSecondary {
+            turn: self.turn,
+            mech: MECH_ID.to_string(),
+            direction: dir,
+        }
+    }
+
+    fn move_mech(&self, dir: GridDirection) -> MechCommand {
+        MechCommand::Move {
+            turn: self.turn,
+            mech: MECH_ID.to_string(),
+            direction: dir,
+        }
+    }
+
+    fn move_to(&self, target: &Point) -> MechCommand {
+        MechCommand::MoveTo {
+            turn: self.turn,
+            mech: MECH_ID.to_string(),
+            target: target.clone(),
+        }
+    }
+
+    fn rotate(&self, dir: GridDirection) -> MechCommand {
+        MechCommand::Rotate {
+            turn: self.turn,
+            mech: MECH_ID.to_string(),
+            direction: dir,
+        }
+    }
+
+    fn raise_shield(&self) -> MechCommand {
+        MechCommand::RaiseShield {
+            turn: self.turn,
+            mech: MECH_ID.to_string(),
+        }
+    }
+
+    fn brace(&self) -> MechCommand {
+        MechCommand::Brace {
+            turn: self.turn,
+            mech: MECH_ID.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MechInstruments, EAX};
+
+    #[test]
+    fn scenario_feeds_instruments() {
+        let scenario = Scenario::new()
+            .with_board(30, 40)
+            .with_enemy("bob", Point::new(3, 8))
+            .with_position(Point::new(3, 4))
+            .with_health(250)
+            .with_register(EAX, RegisterValue::Number(7));
+        let mech = scenario.mech();
+        assert_eq!(mech.position(), Point::new(3, 4));
+        assert_eq!(mech.hull_integrity(), 250);
+        assert_eq!(mech.register_number(EAX), Some(7));
+        assert_eq!(mech.world_size().width, 30);
+        let scan = mech.last_radar_scan().unwrap();
+        assert_eq!(scan.len(), 1);
+        assert!(scan[0].foe);
+        assert_eq!(scan[0].distance, 4);
+    }
+
+    #[test]
+    fn assertion_helpers() {
+        let scenario = Scenario::new()
+            .with_position(Point::new(5, 5))
+            .with_enemy("far", Point::new(15, 5))
+            .with_enemy("near", Point::new(5, 7));
+        let mech = scenario.mech();
+        let commands = vec![mech.fire_primary(mech.direction_to(&Point::new(5, 7)))];
+        scenario.assert_within_power(&commands);
+        scenario.assert_fires_toward_nearest_foe(&commands);
+    }
+
+    #[test]
+    fn mock_starts_from_scenario() {
+        let scenario = Scenario::new()
+            .with_turn(3)
+            .with_position(Point::new(2, 2))
+            .with_enemy("bob", Point::new(2, 6))
+            .with_register(EAX, RegisterValue::Number(7));
+        let mut mech = scenario.mock();
+        assert_eq!(mech.position(), Point::new(2, 2));
+        assert_eq!(mech.register_number(EAX), Some(7));
+        assert_eq!(mech.last_radar_scan().unwrap().len(), 1);
+        assert_eq!(mech.cooldown(WeaponType::Primary), 0);
+
+        mech.random = 50;
+        mech.hull_integrity = 1;
+        assert_eq!(mech.random_number(1, 6), 6);
+        assert_eq!(mech.hull_integrity(), 1);
+        match mech.fire_primary(GridDirection::North) {
+            MechCommand::FirePrimary { turn, mech, .. } => {
+                assert_eq!((turn, mech.as_str()), (3, MECH_ID))
+            }
+            cmd => panic!("unexpected command {:?}", cmd),
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn overspending_fails_assertion() {
+        let scenario = Scenario::new();
+        let mech = scenario.mech();
+        scenario.assert_within_power(&[
+            mech.fire_secondary(GridDirection::North),
+            mech.request_radar(),
+        ]);
+    }
+}
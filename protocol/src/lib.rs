@@ -11,6 +11,17 @@ pub struct MechInfo {
     pub id: String,
 }
 
+impl From<&MechInfo> for wasmdome_domain::turns::Entrant {
+    fn from(mech: &MechInfo) -> Self {
+        wasmdome_domain::turns::Entrant {
+            id: mech.id.to_string(),
+            team: mech.team.to_string(),
+            avatar: mech.avatar.to_string(),
+            name: mech.name.to_string(),
+        }
+    }
+}
+
 pub mod events {
     use chrono::prelude::*;
     use domain::events::EndCause;
@@ -42,6 +53,10 @@ pub mod events {
             actors: Vec<String>,
             board_height: u32,
             board_width: u32,
+            #[serde(default)]
+            terrain: Vec<domain::Obstacle>,
+            #[serde(default)]
+            seed: u64,
             start_time: DateTime<Utc>,
         },
         MatchCompleted {
@@ -60,11 +75,19 @@ pub mod events {
             turn: u32,
             turn_event: domain::events::GameEvent,
         },
+        /// Opens the match's stream of events with the parameters it is played under, so
+        /// that a recording of the match can be played back on the right board. The seed
+        /// is withheld
+        MatchStarted {
+            match_id: String,
+            parameters: domain::MatchParameters,
+        },
     }
 }
 
 pub mod commands {
     use crate::MechInfo;
+    use std::collections::HashMap;
     use wasmdome_domain as domain;
 
     pub fn arena_control_subject() -> String {
@@ -75,6 +98,15 @@ pub mod commands {
     pub enum ArenaControlCommand {
         StartMatch(CreateMatch),
         QueryMechs,
+        AbortMatch(AbortMatch),
+        QueryMatch(QueryMatch),
+    }
+
+    /// Requests that a running match be stopped at the end of its current turn
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct AbortMatch {
+        pub match_id: String,
+        pub reason: String,
     }
 
     /// Sent on a match subject to tell a given mech to take its turn. The response
@@ -85,7 +117,29 @@ pub mod commands {
         pub actor: String,
         pub match_id: String,
         pub turn: u32,
+        /// The match as this actor is allowed to see it, containing only its own mech
+        /// and radar results
         pub state: domain::state::MatchState,
+        /// Seeds the mech's random numbers for this turn. It is derived from the match's
+        /// seed, which the mech isn't allowed to see
+        #[serde(default)]
+        pub rng_seed: u64,
+    }
+
+    impl TakeTurn {
+        /// Prepares the request for a mech to take its turn
+        pub fn new(state: &domain::state::MatchState, actor: &str, turn: u32) -> TakeTurn {
+            TakeTurn {
+                actor: actor.to_string(),
+                match_id: state.parameters.match_id.to_string(),
+                turn,
+                state: domain::state::MatchState::view_for(state, actor),
+                rng_seed: domain::SeededRng::derive_seed(
+                    state.parameters.seed,
+                    &format!("{}-{}", actor, turn),
+                ),
+            }
+        }
     }
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -102,12 +156,49 @@ pub mod commands {
         pub board_width: u32,
         pub max_turns: u32,
         pub aps_per_turn: u32,
+        /// Optional weapon, damage, and action point rules for the match. The standard
+        /// ruleset is used when this is omitted
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub ruleset: Option<domain::Ruleset>,
+        /// Walls, cover, and pits to place on the board. The board is empty when this is omitted
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub terrain: Vec<domain::Obstacle>,
+        /// Whether mechs take their turns one after the other or all at once. Turns are
+        /// sequential when this is omitted
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub turn_mode: Option<domain::TurnMode>,
+        /// The order in which mechs act during each turn of a sequential match. Mechs act
+        /// in the order of `actors` when this is omitted
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub turn_order: Option<domain::TurnOrder>,
+        /// The seed driving everything random about the match. Starting a match with the same
+        /// mechs and seed reproduces the same match. A random seed is chosen when this is omitted
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub seed: Option<u64>,
+        /// Assigns actors to teams, making this a team match won by the last team standing.
+        /// Actors left out keep the team from their `npc` tag
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        pub teams: HashMap<String, String>,
     }
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct MechQueryResponse {
         pub mechs: Vec<MechInfo>,
     }
+
+    /// Requests the state of a match as the arena last saved it, so that spectators can
+    /// join a match that's already being played
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct QueryMatch {
+        pub match_id: String,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct MatchQueryResponse {
+        /// The match as spectators are allowed to see it, or nothing if the arena doesn't
+        /// know of it
+        pub state: Option<domain::state::MatchState>,
+    }
 }
 
 pub mod tools {
@@ -0,0 +1,227 @@
+//! Hosts compiled mechs in-process so that a match can be run without a lattice
+
+use crate::domain::{commands::MechCommand, MatchParameters};
+use crate::protocol::{
+    commands::{TakeTurn, TakeTurnResponse},
+    MechInfo, OP_TAKE_TURN,
+};
+use runner::{MatchRunner, TurnHandler};
+use std::{
+    error::Error,
+    fs,
+    path::Path,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+use wapc::WapcHost;
+use wascap::jwt::Actor;
+
+/// The capability a mech module must be signed with to take part in a match
+const ENGINE_CAPABILITY: &str = "wasmdome:engine";
+/// How long each mech's turn stays on screen when watching a local match
+const FRAME_DELAY: Duration = Duration::from_millis(250);
+
+/// A turn handed to a mech's module, tagged with the turn it is for
+type TurnRequest = (u32, Vec<u8>);
+/// What a mech's module answered for a turn, tagged with the turn it was for
+type TurnReply = (u32, Result<Vec<u8>, String>);
+
+/// A signed mech module running in an embedded WebAssembly runtime. The module runs on a
+/// thread of its own so that a turn can be abandoned once the match's turn timeout has
+/// passed, rather than waiting on a mech that may never answer
+pub(crate) struct LocalMech {
+    info: MechInfo,
+    requests: Sender<TurnRequest>,
+    replies: Receiver<TurnReply>,
+    /// Zero leaves turns without a time limit
+    turn_timeout: Duration,
+}
+
+impl LocalMech {
+    /// Loads a signed mech module, refusing modules whose claims are invalid or that
+    /// weren't signed for the arena
+    pub(crate) fn from_file(path: &Path) -> Result<LocalMech, Box<dyn Error>> {
+        let buf = fs::read(path)?;
+        let token = wascap::wasm::extract_claims(&buf)?
+            .ok_or_else(|| format!("{} is not a signed module", path.display()))?;
+        let validation = wascap::jwt::validate_token::<Actor>(&token.jwt)?;
+        if !validation.signature_valid || validation.expired || validation.cannot_use_yet {
+            return Err(format!("{} has invalid or expired claims", path.display()).into());
+        }
+        let claims = token.claims;
+        let actor = claims.metadata.unwrap_or_else(|| Actor {
+            name: None,
+            module_hash: String::new(),
+            tags: None,
+            caps: None,
+            rev: None,
+            ver: None,
+            provider: false,
+        });
+        if !actor
+            .caps
+            .unwrap_or_default()
+            .contains(&ENGINE_CAPABILITY.to_string())
+        {
+            return Err(format!(
+                "{} is not signed with the {} capability",
+                path.display(),
+                ENGINE_CAPABILITY
+            )
+            .into());
+        }
+        let tags = actor.tags.unwrap_or_default();
+        let info = MechInfo {
+            id: claims.subject,
+            avatar: get_avatar(&tags),
+            name: actor.name.unwrap_or_else(|| "Anonymous Mech".to_string()),
+            team: get_team(&tags),
+        };
+        let (requests, replies) = spawn_module(buf)?;
+        Ok(LocalMech {
+            info,
+            requests,
+            replies,
+            turn_timeout: Duration::from_millis(0),
+        })
+    }
+
+    fn request_turn(&self, turn: &TakeTurn) -> Result<TakeTurnResponse, Box<dyn Error>> {
+        self.requests
+            .send((turn.turn, codec::serialize(turn)?))
+            .map_err(|_| "the module has stopped")?;
+        let started = Instant::now();
+        loop {
+            let reply = if self.turn_timeout.as_millis() == 0 {
+                self.replies
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected)
+            } else {
+                let remaining = self.turn_timeout.checked_sub(started.elapsed());
+                self.replies
+                    .recv_timeout(remaining.unwrap_or_else(|| Duration::from_millis(0)))
+            };
+            match reply {
+                // a late answer to a turn that was already abandoned
+                Ok((t, _)) if t != turn.turn => continue,
+                Ok((_, res)) => return codec::deserialize(&res?),
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(
+                        format!("no answer within {}ms", self.turn_timeout.as_millis()).into(),
+                    )
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err("the module has stopped".into()),
+            }
+        }
+    }
+}
+
+/// Starts a module on a thread of its own, which takes turns as they are requested until
+/// the mech is dropped. A module stuck in a turn keeps its thread until the process exits
+fn spawn_module(
+    buf: Vec<u8>,
+) -> Result<(Sender<TurnRequest>, Receiver<TurnReply>), Box<dyn Error>> {
+    let (req_tx, req_rx) = mpsc::channel::<TurnRequest>();
+    let (reply_tx, reply_rx) = mpsc::channel();
+    let (ready_tx, ready_rx) = mpsc::channel();
+    thread::spawn(move || {
+        // mechs have no capabilities to call other than the arena itself
+        let host = WapcHost::new(
+            |_id, _binding, namespace, operation, _payload| {
+                Err(format!("{} is not available to mechs ({})", namespace, operation).into())
+            },
+            &buf,
+            None,
+        );
+        let host = match host {
+            Ok(host) => {
+                let _ = ready_tx.send(Ok(()));
+                host
+            }
+            Err(e) => {
+                let _ = ready_tx.send(Err(e.to_string()));
+                return;
+            }
+        };
+        for (turn, payload) in req_rx {
+            let res = host.call(OP_TAKE_TURN, &payload).map_err(|e| e.to_string());
+            if reply_tx.send((turn, res)).is_err() {
+                break;
+            }
+        }
+    });
+    ready_rx
+        .recv()
+        .map_err(|_| "the module failed to start")??;
+    Ok((req_tx, reply_rx))
+}
+
+impl TurnHandler for LocalMech {
+    fn take_turn(&mut self, turn: &TakeTurn) -> Vec<MechCommand> {
+        match self.request_turn(turn) {
+            Ok(tr) => tr.commands,
+            Err(e) => {
+                eprintln!(
+                    "Mech {} failed to take turn {}: {}",
+                    self.info.name, turn.turn, e
+                );
+                vec![]
+            }
+        }
+    }
+}
+
+/// Plays a match between the mechs in the given modules without a lattice
+pub(crate) fn run_match(
+    paths: &[impl AsRef<Path>],
+    parameters: MatchParameters,
+    watch: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut runner = MatchRunner::new(parameters.clone());
+    let mut ids: Vec<String> = Vec::new();
+    for path in paths {
+        let mut mech = LocalMech::from_file(path.as_ref())?;
+        mech.turn_timeout = Duration::from_millis(parameters.ruleset.turn_timeout_millis);
+        // the same module can be entered more than once
+        let copies = ids
+            .iter()
+            .filter(|id| id.starts_with(&mech.info.id))
+            .count();
+        if copies > 0 {
+            mech.info.id = format!("{}-{}", mech.info.id, copies + 1);
+        }
+        println!("Loaded mech {} ({})", mech.info.name, mech.info.id);
+        ids.push(mech.info.id.to_string());
+        runner = runner.with_mech(mech.info.clone(), mech);
+    }
+    let result = runner.run()?;
+    if watch {
+        crate::watch::play(parameters.clone(), &result.events, FRAME_DELAY);
+    }
+    println!(
+        "Match \"{}\" completed.\nCause: {}\nSeed: {}",
+        parameters.match_id,
+        result
+            .state
+            .completed
+            .map_or("unknown".to_string(), |cause| format!("{:?}", cause)),
+        parameters.seed
+    );
+    Ok(())
+}
+
+fn get_team(tags: &[String]) -> String {
+    if tags.contains(&"npc".to_string()) {
+        "boylur".to_string()
+    } else {
+        "earth".to_string()
+    }
+}
+
+fn get_avatar(tags: &[String]) -> String {
+    match tags.iter().find(|t| t.starts_with("avatar-")) {
+        Some(t) => t.replace("avatar-", ""),
+        None => "none".to_string(),
+    }
+}
@@ -1,11 +1,17 @@
+extern crate wascc_codec as codec;
 extern crate wasmdome_domain as domain;
+extern crate wasmdome_match_runner as runner;
 extern crate wasmdome_protocol as protocol;
 
+mod local;
+mod replay;
+mod watch;
+
 use crossbeam_channel::unbounded;
 use protocol::commands::{ArenaControlCommand::*, CreateMatch, MechQueryResponse};
 use protocol::events::ArenaEvent;
 use protocol::scheduler::StoredMatch;
-use std::{error::Error, path::PathBuf};
+use std::{collections::HashMap, error::Error, path::PathBuf};
 use structopt::clap::AppSettings;
 use structopt::StructOpt;
 use uuid::Uuid;
@@ -26,7 +32,8 @@ struct Cli {
 
 #[derive(Debug, Clone, StructOpt)]
 struct CliCommand {
-    /// Path to the mechs (actors) used by wasmdome
+    /// Path to the signed mechs (actors) to host locally. When given, `run` plays the match
+    /// in-process instead of on the lattice
     #[structopt(short = "m", long = "mech", parse(from_os_str))]
     actor_paths: Vec<PathBuf>,
 
@@ -38,7 +45,7 @@ struct CliCommand {
 enum WasmdomeAction {
     /// Query the schedule of upcoming matches
     Schedule,
-    /// Run a wasmdome match using the local lattice
+    /// Run a wasmdome match, either between the mechs given with --mech or using the local lattice
     Run {
         /// Maximum number of turns in the match
         #[structopt(short = "t", long = "max_turns")]
@@ -51,28 +58,100 @@ enum WasmdomeAction {
         /// Board width
         #[structopt(short = "w", long = "width")]
         board_width: u32,
+
+        /// Resolve every mech's turn at the same time instead of one after the other
+        #[structopt(long = "simultaneous")]
+        simultaneous: bool,
+
+        /// Seed for the match's random numbers. Re-running the same mechs with the same seed replays the same match
+        #[structopt(short = "s", long = "seed")]
+        seed: Option<u64>,
+
+        /// Draw the board in the terminal as the match is played
+        #[structopt(long = "watch")]
+        watch: bool,
+
+        /// Milliseconds each mech has to take its turn in a match between the mechs given with --mech, or 0 for no limit
+        #[structopt(long = "turn_timeout", default_value = "2000")]
+        turn_timeout_millis: u64,
+    },
+    /// Watch a match being played on the lattice
+    Watch {
+        /// The id of the match to watch
+        match_id: String,
+    },
+    /// Play back a recorded match, fetched from the historian
+    Replay {
+        /// The id of the match to replay
+        match_id: String,
     },
 }
 
 fn handle_command(cmd: CliCommand) -> std::result::Result<(), Box<dyn ::std::error::Error>> {
-    let nc = match nats::connect("127.0.0.1") {
-        Err(_e) => {
-            println!("Couldn't connect to the lattice. Is NATS running?");
-            return Ok(());
-        }
-        Ok(v) => v,
-    };
     match cmd.action {
-        WasmdomeAction::Schedule => check_schedule(nc)?,
         WasmdomeAction::Run {
             max_turns,
             board_height,
             board_width,
-        } => run_match(nc, max_turns, board_height, board_width)?,
+            simultaneous,
+            seed,
+            watch,
+            turn_timeout_millis,
+        } if !cmd.actor_paths.is_empty() => {
+            let params = domain::MatchParameters {
+                match_id: Uuid::new_v4().to_string(),
+                width: board_width,
+                height: board_height,
+                max_turns,
+                aps_per_turn: domain::state::APS_PER_TURN,
+                turn_mode: if simultaneous {
+                    domain::TurnMode::Simultaneous
+                } else {
+                    domain::TurnMode::Sequential
+                },
+                seed: seed.unwrap_or_else(|| Uuid::new_v4().as_u128() as u64),
+                ruleset: domain::Ruleset {
+                    turn_timeout_millis,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            local::run_match(&cmd.actor_paths, params, watch)?
+        }
+        WasmdomeAction::Schedule => check_schedule(connect()?)?,
+        WasmdomeAction::Watch { match_id } => {
+            watch::Watch::join(&connect()?, &match_id)?.run()?;
+        }
+        WasmdomeAction::Replay { match_id } => {
+            let (params, events) = replay::fetch(&connect()?, &match_id)?;
+            replay::play(replay::Playback::new(params, events));
+        }
+        WasmdomeAction::Run {
+            max_turns,
+            board_height,
+            board_width,
+            simultaneous,
+            seed,
+            watch,
+            ..
+        } => run_match(
+            connect()?,
+            max_turns,
+            board_height,
+            board_width,
+            simultaneous,
+            seed,
+            watch,
+        )?,
     };
     Ok(())
 }
 
+fn connect() -> Result<nats::Connection, Box<dyn Error>> {
+    nats::connect("127.0.0.1")
+        .map_err(|_e| "Couldn't connect to the lattice. Is NATS running?".into())
+}
+
 fn check_schedule(nc: nats::Connection) -> Result<(), Box<dyn Error>> {
     let res = nc.request_timeout(
         "wasmdome.public.arena.schedule",
@@ -122,9 +201,24 @@ fn run_match(
     max_turns: u32,
     board_height: u32,
     board_width: u32,
+    simultaneous: bool,
+    seed: Option<u64>,
+    watch: bool,
 ) -> Result<(), Box<dyn Error>> {
     let match_id = Uuid::new_v4().to_string();
     let sub = nc.subscribe("wasmdome.public.arena.events")?;
+    let watcher = if watch {
+        let params = domain::MatchParameters {
+            match_id: match_id.clone(),
+            width: board_width,
+            height: board_height,
+            max_turns,
+            ..Default::default()
+        };
+        Some(watch::Watch::subscribe(&nc, params)?)
+    } else {
+        None
+    };
 
     let req = nc.request_timeout(
         "wasmdome.internal.arena.control",
@@ -155,6 +249,16 @@ fn run_match(
         board_width,
         max_turns,
         aps_per_turn: domain::state::APS_PER_TURN,
+        ruleset: None,
+        terrain: Vec::new(),
+        turn_mode: if simultaneous {
+            Some(domain::TurnMode::Simultaneous)
+        } else {
+            None
+        },
+        turn_order: None,
+        seed,
+        teams: HashMap::new(),
     });
 
     nc.request_timeout(
@@ -163,17 +267,40 @@ fn run_match(
         std::time::Duration::from_millis(500),
     )?;
 
+    if let Some(watcher) = watcher {
+        let state = watcher.run()?;
+        println!(
+            "Match \"{}\" completed.\nCause: {}\nSeed: {}",
+            match_id,
+            state
+                .completed
+                .map_or("unknown".to_string(), |cause| format!("{:?}", cause)),
+            state.parameters.seed
+        );
+        return Ok(());
+    }
+
     let (s, r) = unbounded();
+    let match_seed = std::cell::Cell::new(seed);
+    let our_match = match_id.clone();
 
     sub.with_handler(move |msg| {
         let msg = serde_json::from_slice(&msg.data).unwrap();
         match msg {
+            // other matches may be running on the same lattice
+            Some(ArenaEvent::MatchStarted { match_id, seed, .. }) if match_id == our_match => {
+                match_seed.set(Some(seed))
+            }
             Some(ArenaEvent::MatchCompleted {
                 match_id, cause, ..
-            }) => {
+            }) if match_id == our_match => {
                 s.send(format!(
-                    "Match \"{}\" completed.\nCause: {:?}",
-                    match_id, cause
+                    "Match \"{}\" completed.\nCause: {:?}\nSeed: {}",
+                    match_id,
+                    cause,
+                    match_seed
+                        .get()
+                        .map_or("unknown".to_string(), |s| s.to_string())
                 ))
                 .unwrap();
             }
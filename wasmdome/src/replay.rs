@@ -0,0 +1,364 @@
+//! Plays back a recorded match in the terminal
+
+use crate::domain::{events::GameEvent, MatchParameters};
+use crate::protocol::events::MatchEvent;
+use crate::watch::{Spectator, CLEAR_SCREEN};
+use std::{
+    error::Error,
+    io::BufRead,
+    sync::mpsc::{self, RecvTimeoutError},
+    time::Duration,
+};
+
+const SUBJECT_TRIGGER_REPLAY: &str = "wasmdome.history.replay";
+/// The historian doesn't mark the end of a replay, so it is over once it goes quiet
+const REPLAY_IDLE_TIMEOUT: Duration = Duration::from_millis(1500);
+const FRAME_DELAY_MILLIS: u64 = 400;
+const MIN_FRAME_DELAY_MILLIS: u64 = 25;
+const MAX_FRAME_DELAY_MILLIS: u64 = 6400;
+
+/// Returns the subject on which the historian replays a match's events
+fn replay_subject(match_id: &str) -> String {
+    format!("wasmdome.match.{}.events.replay", match_id)
+}
+
+/// Asks the historian to replay a match and collects every event it publishes, along
+/// with the parameters the match was played under. Matches recorded before the historian
+/// kept their parameters are played back on an empty board sized to fit the mechs
+pub(crate) fn fetch(
+    nc: &nats::Connection,
+    match_id: &str,
+) -> Result<(MatchParameters, Vec<GameEvent>), Box<dyn Error>> {
+    let sub = nc.subscribe(&replay_subject(match_id))?;
+    let trigger = serde_json::json!({ "match_id": match_id });
+    nc.publish(SUBJECT_TRIGGER_REPLAY, &serde_json::to_vec(&trigger)?)?;
+
+    let mut parameters = None;
+    let mut events = Vec::new();
+    while let Ok(msg) = sub.next_timeout(REPLAY_IDLE_TIMEOUT) {
+        match serde_json::from_slice(&msg.data) {
+            Ok(MatchEvent::MatchStarted { parameters: p, .. }) => parameters = Some(p),
+            Ok(MatchEvent::TurnEvent { turn_event, .. }) => {
+                let finished = matches!(turn_event, GameEvent::GameFinished { .. });
+                events.push(turn_event);
+                if finished {
+                    break;
+                }
+            }
+            Err(_) => {}
+        }
+    }
+    if events.is_empty() {
+        return Err(format!(
+            "No events were replayed for match {}. Is the historian running?",
+            match_id
+        )
+        .into());
+    }
+    let parameters = parameters.unwrap_or_else(|| MatchParameters {
+        match_id: match_id.to_string(),
+        ..Default::default()
+    });
+    Ok((parameters, events))
+}
+
+/// A command typed by the viewer while a match is being played back
+#[derive(Debug, Clone, PartialEq)]
+enum Control {
+    TogglePause,
+    NextTurn,
+    PreviousTurn,
+    Faster,
+    Slower,
+    JumpTo(u32),
+    Quit,
+}
+
+impl Control {
+    fn parse(line: &str) -> Option<Control> {
+        let line = line.trim();
+        let control = match line {
+            "" | "p" => Control::TogglePause,
+            "n" => Control::NextTurn,
+            "b" => Control::PreviousTurn,
+            "+" => Control::Faster,
+            "-" => Control::Slower,
+            "q" => Control::Quit,
+            _ => Control::JumpTo(line.trim_start_matches('g').trim().parse().ok()?),
+        };
+        Some(control)
+    }
+}
+
+/// A recorded match divided into frames, one for the end of each mech's turn, that
+/// rebuilds the state of the match for whichever frame is shown
+pub(crate) struct Playback {
+    parameters: MatchParameters,
+    events: Vec<GameEvent>,
+    /// The number of events leading up to each frame, along with the frame's turn
+    frames: Vec<(usize, u32)>,
+    frame: usize,
+    spectator: Spectator,
+    /// The number of events applied to the spectator
+    applied: usize,
+}
+
+impl Playback {
+    pub(crate) fn new(parameters: MatchParameters, events: Vec<GameEvent>) -> Playback {
+        let mut frames = Vec::new();
+        let mut turn = 0;
+        for (i, evt) in events.iter().enumerate() {
+            match evt {
+                GameEvent::MechTurnCompleted { turn: t, .. } => {
+                    turn = *t;
+                    frames.push((i + 1, turn));
+                }
+                GameEvent::GameFinished { .. } => frames.push((i + 1, turn)),
+                _ => {}
+            }
+        }
+        // events after the last completed turn still get a frame of their own
+        if frames.last().map(|(count, _)| *count) != Some(events.len()) {
+            frames.push((events.len(), turn));
+        }
+        let mut playback = Playback {
+            spectator: Spectator::new(parameters.clone()),
+            parameters,
+            events,
+            frames,
+            frame: 0,
+            applied: 0,
+        };
+        playback.seek(0);
+        playback
+    }
+
+    pub(crate) fn turn(&self) -> u32 {
+        self.frames[self.frame].1
+    }
+
+    pub(crate) fn last_turn(&self) -> u32 {
+        self.frames.last().map_or(0, |(_, turn)| *turn)
+    }
+
+    pub(crate) fn at_end(&self) -> bool {
+        self.frame + 1 == self.frames.len()
+    }
+
+    pub(crate) fn spectator(&self) -> &Spectator {
+        &self.spectator
+    }
+
+    /// Moves on to the next frame, returning false if there isn't one
+    pub(crate) fn advance(&mut self) -> bool {
+        if self.at_end() {
+            return false;
+        }
+        self.seek(self.frame + 1);
+        true
+    }
+
+    /// Shows the end of the current turn or, if it is already shown, of the next one
+    pub(crate) fn next_turn(&mut self) {
+        let turn = self.turn();
+        let end_of_turn = self.last_frame_until(turn);
+        if end_of_turn > self.frame {
+            self.seek(end_of_turn);
+        } else if let Some((_, next)) = self.frames.iter().find(|(_, t)| *t > turn) {
+            self.seek(self.last_frame_until(*next));
+        }
+    }
+
+    /// Shows the end of the previous turn, or the start of the match on the first turn
+    pub(crate) fn previous_turn(&mut self) {
+        match self.turn().checked_sub(1) {
+            Some(turn) => self.jump_to(turn),
+            None => self.seek(0),
+        }
+    }
+
+    /// Shows the end of the given turn, or the last turn of the match if it ended sooner
+    pub(crate) fn jump_to(&mut self, turn: u32) {
+        let frame = self.last_frame_until(turn);
+        self.seek(frame);
+    }
+
+    fn last_frame_until(&self, turn: u32) -> usize {
+        self.frames
+            .iter()
+            .rposition(|(_, t)| *t <= turn)
+            .unwrap_or(0)
+    }
+
+    /// Brings the match up to the given frame by applying its events. Going backward
+    /// replays the match from its first event
+    fn seek(&mut self, frame: usize) {
+        let count = self.frames[frame].0;
+        if count < self.applied {
+            self.spectator = Spectator::new(self.parameters.clone());
+            self.applied = 0;
+        }
+        for evt in &self.events[self.applied..count] {
+            self.spectator.apply(evt);
+        }
+        self.applied = count;
+        self.frame = frame;
+    }
+}
+
+/// Plays back a match, taking commands from the terminal until the viewer quits
+pub(crate) fn play(mut playback: Playback) {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut delay = FRAME_DELAY_MILLIS;
+    let mut paused = false;
+    loop {
+        print!(
+            "{}{}\nTurn {}/{}  {}  {}ms per frame\n\
+             [enter] pause/play  n next turn  b previous turn  +/- speed  g <turn> jump  q quit\n",
+            CLEAR_SCREEN,
+            playback.spectator().render(),
+            playback.turn(),
+            playback.last_turn(),
+            if paused {
+                "paused"
+            } else if playback.at_end() {
+                "finished"
+            } else {
+                "playing"
+            },
+            delay
+        );
+        let input = if paused || playback.at_end() {
+            match rx.recv() {
+                Ok(line) => line,
+                Err(_) => return, // nothing more can be typed
+            }
+        } else {
+            match rx.recv_timeout(Duration::from_millis(delay)) {
+                Ok(line) => line,
+                Err(e) => {
+                    if e == RecvTimeoutError::Disconnected {
+                        std::thread::sleep(Duration::from_millis(delay));
+                    }
+                    playback.advance();
+                    continue;
+                }
+            }
+        };
+        match Control::parse(&input) {
+            Some(Control::TogglePause) => paused = !paused,
+            Some(Control::NextTurn) => {
+                paused = true;
+                playback.next_turn();
+            }
+            Some(Control::PreviousTurn) => {
+                paused = true;
+                playback.previous_turn();
+            }
+            Some(Control::Faster) => delay = (delay / 2).max(MIN_FRAME_DELAY_MILLIS),
+            Some(Control::Slower) => delay = (delay * 2).min(MAX_FRAME_DELAY_MILLIS),
+            Some(Control::JumpTo(turn)) => {
+                paused = true;
+                playback.jump_to(turn);
+            }
+            Some(Control::Quit) => return,
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::domain::{events::EndCause, Obstacle, Point, TerrainType};
+
+    fn gen_events() -> Vec<GameEvent> {
+        let mut events = vec![GameEvent::MechSpawned {
+            mech: "al".to_string(),
+            position: Point::new(1, 1),
+            team: "earth".to_string(),
+            avatar: "none".to_string(),
+            name: "al".to_string(),
+        }];
+        for turn in 0..3 {
+            events.push(GameEvent::PositionUpdated {
+                mech: "al".to_string(),
+                position: Point::new(2 + turn as i32, 1),
+            });
+            events.push(GameEvent::MechTurnCompleted {
+                mech: "al".to_string(),
+                turn,
+            });
+            events.push(GameEvent::MatchTurnCompleted { new_turn: turn + 1 });
+        }
+        events.push(GameEvent::GameFinished {
+            cause: EndCause::MaxTurnsCompleted {
+                survivors: vec!["al".to_string()],
+            },
+        });
+        events
+    }
+
+    fn position(playback: &Playback) -> i32 {
+        playback.spectator().state().mechs["al"].position.x
+    }
+
+    #[test]
+    fn steps_between_turns() {
+        let mut playback = Playback::new(MatchParameters::default(), gen_events());
+        assert_eq!((playback.turn(), position(&playback)), (0, 2));
+        assert_eq!(playback.last_turn(), 2);
+
+        playback.next_turn();
+        assert_eq!((playback.turn(), position(&playback)), (1, 3));
+        playback.jump_to(7);
+        assert!(playback.at_end());
+        assert!(playback.spectator().state().completed.is_some());
+        assert!(!playback.advance());
+
+        playback.previous_turn();
+        assert_eq!((playback.turn(), position(&playback)), (1, 3));
+        assert!(playback.spectator().state().completed.is_none());
+        playback.jump_to(0);
+        playback.previous_turn();
+        assert_eq!((playback.turn(), position(&playback)), (0, 2));
+    }
+
+    #[test]
+    fn plays_back_on_the_recorded_board() {
+        let parameters = MatchParameters {
+            width: 8,
+            height: 4,
+            terrain: vec![Obstacle::new(Point::new(6, 3), TerrainType::Wall)],
+            ..Default::default()
+        };
+        let mut playback = Playback::new(parameters, gen_events());
+        playback.jump_to(2);
+        let board = playback.spectator().render();
+        let rows: Vec<&str> = board.lines().skip(2).take(4).collect();
+        // row 0 is the top of the board, y = 3
+        assert_eq!(rows[0], "|      # |");
+        assert_eq!(rows[3 - 1], "|    A   |");
+    }
+
+    #[test]
+    fn parses_controls() {
+        assert_eq!(Control::parse(""), Some(Control::TogglePause));
+        assert_eq!(Control::parse("n\n"), Some(Control::NextTurn));
+        assert_eq!(Control::parse("g 12"), Some(Control::JumpTo(12)));
+        assert_eq!(Control::parse("7"), Some(Control::JumpTo(7)));
+        assert_eq!(Control::parse("what"), None);
+    }
+}
@@ -0,0 +1,575 @@
+//! Renders a match in the terminal as its events arrive
+
+use crate::domain::{
+    events::GameEvent,
+    eventsourcing::Aggregate,
+    state::{Match, MatchState},
+    DamageSource, MatchParameters, Obstacle, Point, TerrainType, WeaponType,
+};
+use crate::protocol::{
+    commands::{arena_control_subject, ArenaControlCommand, MatchQueryResponse, QueryMatch},
+    events::{events_subject, ArenaEvent, MatchEvent},
+};
+use std::{
+    collections::VecDeque,
+    error::Error,
+    time::{Duration, Instant},
+};
+
+/// The number of event log lines shown beneath the board
+const LOG_LINES: usize = 10;
+const HEALTH_BAR_WIDTH: usize = 20;
+/// How long to wait without hearing from the match before giving up on it
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Clears the terminal and moves the cursor back to the top left
+pub(crate) const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// Rebuilds a match from its events and draws it as a grid of characters, along with
+/// the health of every mech, the shots fired during the current turn and a log of what
+/// has happened recently
+pub(crate) struct Spectator {
+    state: MatchState,
+    /// Mechs in the order they spawned, which decides the letter each is drawn with
+    mechs: Vec<String>,
+    /// Characters drawn over the board for the shots fired during the current turn
+    effects: Vec<(Point, char)>,
+    /// The mech and weapon of the last shot fired, until it hits something
+    firing: Option<(String, WeaponType)>,
+    log: VecDeque<String>,
+    frame_complete: bool,
+}
+
+impl Spectator {
+    pub(crate) fn new(parameters: MatchParameters) -> Self {
+        Spectator {
+            state: MatchState::new_with_parameters(parameters),
+            mechs: Vec::new(),
+            effects: Vec::new(),
+            firing: None,
+            log: VecDeque::new(),
+            frame_complete: false,
+        }
+    }
+
+    /// Picks up a match part way through from the arena's state of it. Mechs spawn in
+    /// the order of their ids, so that's the order they're drawn in
+    pub(crate) fn resume(state: MatchState) -> Self {
+        let mut mechs: Vec<_> = state.mechs.keys().cloned().collect();
+        mechs.sort();
+        Spectator {
+            state,
+            mechs,
+            ..Spectator::new(MatchParameters::default())
+        }
+    }
+
+    pub(crate) fn state(&self) -> &MatchState {
+        &self.state
+    }
+
+    /// Fills in the board and seed once the arena announces the match. Mechs may already
+    /// have spawned by the time the announcement arrives
+    pub(crate) fn match_started(
+        &mut self,
+        width: u32,
+        height: u32,
+        terrain: Vec<Obstacle>,
+        seed: u64,
+    ) {
+        self.state.parameters.seed = seed;
+        self.state.parameters.width = width;
+        self.state.parameters.height = height;
+        self.state.parameters.terrain = terrain.clone();
+        self.state.game_board.width = width;
+        self.state.game_board.height = height;
+        self.state.game_board.terrain = terrain;
+    }
+
+    /// Applies an event to the match, returning whether it completes a frame worth
+    /// drawing: the end of a mech's turn, or of the match
+    pub(crate) fn apply(&mut self, evt: &GameEvent) -> bool {
+        // a match turn ending doesn't start a new frame, so the last shots stay visible
+        if self.frame_complete && !matches!(evt, GameEvent::MatchTurnCompleted { .. }) {
+            self.frame_complete = false;
+            self.effects.clear();
+        }
+        self.track_shots(evt);
+        if let Some(line) = self.describe(evt) {
+            self.log.push_back(line);
+            while self.log.len() > LOG_LINES {
+                self.log.pop_front();
+            }
+        }
+        if let Ok(state) = Match::apply_event(&self.state, evt) {
+            self.state = state;
+            if let GameEvent::MechSpawned { mech, .. } = evt {
+                if !self.mechs.contains(mech) {
+                    self.mechs.push(mech.to_string());
+                }
+            }
+        }
+        let complete = matches!(
+            evt,
+            GameEvent::MechTurnCompleted { .. } | GameEvent::GameFinished { .. }
+        );
+        self.frame_complete |= complete;
+        complete
+    }
+
+    /// Draws the path of a shot from the mech that fired it to the first thing it hit,
+    /// along with the area caught in the blast of a secondary weapon
+    fn track_shots(&mut self, evt: &GameEvent) {
+        let impact = match evt {
+            GameEvent::WeaponFired { mech, weapon } => {
+                self.firing = Some((mech.to_string(), *weapon));
+                None
+            }
+            GameEvent::DamageTaken {
+                damage_target,
+                damage_source: DamageSource::MechWeapon(shooter),
+                ..
+            } => self
+                .state
+                .mechs
+                .get(damage_target)
+                .map(|m| (shooter, m.position.clone())),
+            GameEvent::TerrainDamaged {
+                position,
+                damage_source: DamageSource::MechWeapon(shooter),
+                ..
+            } => Some((shooter, position.clone())),
+            _ => None,
+        };
+        let (shooter, target) = match impact {
+            Some((shooter, target)) => (shooter, target),
+            None => return,
+        };
+        let weapon = match &self.firing {
+            Some((mech, weapon)) if mech == shooter => *weapon,
+            _ => return, // only the first hit of a shot is its point of impact
+        };
+        self.firing = None;
+        if let Some(origin) = self.state.mechs.get(shooter).map(|m| m.position.clone()) {
+            for p in line_between(&origin, &target) {
+                self.effects.push((p, '.'));
+            }
+        }
+        if weapon == WeaponType::Secondary {
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    if dx != 0 || dy != 0 {
+                        self.effects
+                            .push((Point::new(target.x + dx, target.y + dy), '*'));
+                    }
+                }
+            }
+        }
+        self.effects.push((target, 'X'));
+    }
+
+    fn name(&self, mech: &str) -> String {
+        self.state
+            .mechs
+            .get(mech)
+            .map_or(mech.to_string(), |m| m.name.to_string())
+    }
+
+    fn describe(&self, evt: &GameEvent) -> Option<String> {
+        let line = match evt {
+            GameEvent::MechSpawned { name, position, .. } => {
+                format!(
+                    "{} entered the arena at ({}, {})",
+                    name, position.x, position.y
+                )
+            }
+            GameEvent::WeaponFired { mech, weapon } => {
+                format!("{} fired its {:?} weapon", self.name(mech), weapon)
+            }
+            GameEvent::DamageTaken {
+                damage_target,
+                damage,
+                damage_source,
+            } => format!(
+                "{} took {} damage from {}",
+                self.name(damage_target),
+                damage,
+                self.source(damage_source)
+            ),
+            GameEvent::DamageBlocked { mech, damage, .. } => {
+                format!("{} blocked {} damage", self.name(mech), damage)
+            }
+            GameEvent::MechDestroyed {
+                damage_target,
+                damage_source,
+                ..
+            } => format!(
+                "{} was destroyed by {}",
+                self.name(damage_target),
+                self.source(damage_source)
+            ),
+            GameEvent::ShieldRaised { mech } => format!("{} raised its shield", self.name(mech)),
+            GameEvent::Braced { mech } => format!("{} braced itself", self.name(mech)),
+            GameEvent::PickupCollected { mech, pickup } => {
+                format!("{} collected {:?}", self.name(mech), pickup.kind)
+            }
+            GameEvent::PenaltyApplied { mech, penalty, .. } => {
+                format!("{} was penalized: {:?}", self.name(mech), penalty)
+            }
+            GameEvent::TurnTimedOut { mech, .. } => format!("{} timed out", self.name(mech)),
+            GameEvent::TerrainDestroyed { position, .. } => {
+                format!("Cover at ({}, {}) was destroyed", position.x, position.y)
+            }
+            GameEvent::MatchTurnCompleted { new_turn } => format!("-- Turn {} --", new_turn),
+            GameEvent::GameFinished { cause } => format!("Match over: {:?}", cause),
+            _ => return None,
+        };
+        Some(line)
+    }
+
+    fn source(&self, source: &DamageSource) -> String {
+        match source {
+            DamageSource::Wall => "a wall".to_string(),
+            DamageSource::Pit => "a pit".to_string(),
+            DamageSource::MechWeapon(mech) => self.name(mech),
+            DamageSource::MechCollision(mech) => format!("colliding with {}", self.name(mech)),
+            DamageSource::Penalty => "a penalty".to_string(),
+        }
+    }
+
+    fn letter(&self, mech: &str) -> char {
+        let index = self.mechs.iter().position(|m| m == mech).unwrap_or(0);
+        (b'A' + (index % 26) as u8) as char
+    }
+
+    /// Draws the board, with north at the top, followed by the mechs and the event log
+    pub(crate) fn render(&self) -> String {
+        let params = &self.state.parameters;
+        let (width, height) = self.board_size();
+        let mut out = format!(
+            "Match {}    Turn {}\n",
+            params.match_id, self.state.turn_status.current
+        );
+        let border = format!("+{}+\n", "-".repeat(width as usize));
+        out.push_str(&border);
+        for y in (0..height).rev() {
+            out.push('|');
+            for x in 0..width {
+                out.push(self.cell(&Point::new(x, y)));
+            }
+            out.push_str("|\n");
+        }
+        out.push_str(&border);
+
+        let max_health = params.ruleset.initial_health.max(1);
+        for (id, mech) in self
+            .mechs
+            .iter()
+            .filter_map(|id| self.state.mechs.get(id).map(|m| (id, m)))
+        {
+            let filled = (mech.health.min(max_health) as usize * HEALTH_BAR_WIDTH
+                + max_health as usize / 2)
+                / max_health as usize;
+            out.push_str(&format!(
+                "{} {:<20} {:<8} [{}{}] {}\n",
+                self.letter(id),
+                mech.name,
+                mech.team,
+                "#".repeat(filled),
+                " ".repeat(HEALTH_BAR_WIDTH - filled),
+                if mech.alive {
+                    format!("{}/{}", mech.health, max_health)
+                } else {
+                    "destroyed".to_string()
+                }
+            ));
+        }
+        out.push('\n');
+        for line in &self.log {
+            out.push_str(line);
+            out.push('\n');
+        }
+        out
+    }
+
+    /// The size of the board, or of the smallest board holding everything seen so far when
+    /// the match's parameters haven't been announced
+    fn board_size(&self) -> (i32, i32) {
+        let params = &self.state.parameters;
+        self.state
+            .mechs
+            .values()
+            .map(|m| &m.position)
+            .chain(self.state.game_board.terrain.iter().map(|o| &o.position))
+            .fold(
+                (params.width.max(1) as i32, params.height.max(1) as i32),
+                |(w, h), p| (w.max(p.x + 1), h.max(p.y + 1)),
+            )
+    }
+
+    fn cell(&self, p: &Point) -> char {
+        if let Some((_, c)) = self.effects.iter().rev().find(|(e, _)| e == p) {
+            return *c;
+        }
+        if let Some((id, mech)) = self
+            .mechs
+            .iter()
+            .filter_map(|id| self.state.mechs.get(id).map(|m| (id, m)))
+            .find(|(_, m)| m.position == *p)
+        {
+            return if mech.alive { self.letter(id) } else { 'x' };
+        }
+        if self.state.pickups.iter().any(|pu| pu.position == *p) {
+            return '+';
+        }
+        match self
+            .state
+            .game_board
+            .terrain
+            .iter()
+            .find(|o| o.position == *p)
+            .map(|o| &o.terrain)
+        {
+            Some(TerrainType::Wall) => '#',
+            Some(TerrainType::Cover { .. }) => '%',
+            Some(TerrainType::Pit) => 'O',
+            None => ' ',
+        }
+    }
+}
+
+/// The points strictly between two others along the straightest line joining them
+fn line_between(from: &Point, to: &Point) -> Vec<Point> {
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let steps = dx.abs().max(dy.abs());
+    (1..steps)
+        .map(|i| {
+            Point::new(
+                from.x + (dx as f64 * i as f64 / steps as f64).round() as i32,
+                from.y + (dy as f64 * i as f64 / steps as f64).round() as i32,
+            )
+        })
+        .collect()
+}
+
+/// Redraws the whole terminal with the spectator's view of the match
+pub(crate) fn draw(spectator: &Spectator) {
+    print!("{}{}", CLEAR_SCREEN, spectator.render());
+}
+
+/// Watches a match being played on the lattice
+pub(crate) struct Watch {
+    match_id: String,
+    events: nats::Subscription,
+    arena: nats::Subscription,
+    spectator: Spectator,
+}
+
+impl Watch {
+    /// Starts listening for the match's events. Subscribing before the match starts
+    /// means the watcher sees every mech spawn
+    pub(crate) fn subscribe(
+        nc: &nats::Connection,
+        parameters: MatchParameters,
+    ) -> Result<Watch, Box<dyn Error>> {
+        let match_id = parameters.match_id.to_string();
+        Ok(Watch {
+            events: nc.subscribe(&events_subject(Some(&match_id)))?,
+            arena: nc.subscribe(&events_subject(None))?,
+            match_id,
+            spectator: Spectator::new(parameters),
+        })
+    }
+
+    /// Starts listening for the events of a match that may already be under way,
+    /// picking up from the arena's state of it. Events the arena published before it
+    /// answered may already be part of that state, so those from turns, or from mechs'
+    /// turns, that the state has already completed are skipped
+    pub(crate) fn join(nc: &nats::Connection, match_id: &str) -> Result<Watch, Box<dyn Error>> {
+        let parameters = MatchParameters {
+            match_id: match_id.to_string(),
+            ..Default::default()
+        };
+        let mut watch = Watch::subscribe(nc, parameters)?;
+        let query = ArenaControlCommand::QueryMatch(QueryMatch {
+            match_id: match_id.to_string(),
+        });
+        let resp = nc.request_timeout(
+            &arena_control_subject(),
+            &serde_json::to_vec(&query)?,
+            Duration::from_millis(1500),
+        )?;
+        let state = match serde_json::from_slice::<MatchQueryResponse>(&resp.data)?.state {
+            Some(state) => state,
+            None => return Ok(watch), // the match hasn't started yet
+        };
+        let (current, taken) = (state.turn_status.current, state.turn_status.taken.clone());
+        watch.spectator = Spectator::resume(state);
+        while let Some(msg) = watch.events.try_next() {
+            if let Ok(MatchEvent::TurnEvent {
+                turn,
+                actor,
+                turn_event,
+                ..
+            }) = serde_json::from_slice(&msg.data)
+            {
+                if turn > current || (turn == current && !taken.contains(&actor)) {
+                    watch.spectator.apply(&turn_event);
+                }
+            }
+        }
+        Ok(watch)
+    }
+
+    /// Draws the match as it is played, returning once it is over or once it has gone
+    /// quiet for too long
+    pub(crate) fn run(mut self) -> Result<MatchState, Box<dyn Error>> {
+        println!("Waiting for events from match {}...", self.match_id);
+        if !self.spectator.state().mechs.is_empty() {
+            draw(&self.spectator); // joined part way through
+        }
+        let mut last_heard = Instant::now();
+        loop {
+            while let Some(msg) = self.arena.try_next() {
+                if let Ok(ArenaEvent::MatchStarted {
+                    match_id,
+                    board_height,
+                    board_width,
+                    terrain,
+                    seed,
+                    ..
+                }) = serde_json::from_slice(&msg.data)
+                {
+                    if match_id == self.match_id {
+                        self.spectator
+                            .match_started(board_width, board_height, terrain, seed);
+                    }
+                }
+            }
+            let msg = match self.events.next_timeout(Duration::from_millis(250)) {
+                Ok(msg) => msg,
+                Err(_) if last_heard.elapsed() < IDLE_TIMEOUT => continue,
+                Err(_) => {
+                    return Err(format!(
+                        "Heard nothing from match {} for {} seconds",
+                        self.match_id,
+                        IDLE_TIMEOUT.as_secs()
+                    )
+                    .into())
+                }
+            };
+            last_heard = Instant::now();
+            if let Ok(MatchEvent::TurnEvent { turn_event, .. }) = serde_json::from_slice(&msg.data)
+            {
+                if self.spectator.apply(&turn_event) {
+                    draw(&self.spectator);
+                }
+                if let GameEvent::GameFinished { .. } = turn_event {
+                    return Ok(self.spectator.state().clone());
+                }
+            }
+        }
+    }
+}
+
+/// Draws a match that has already been played, one frame at a time
+pub(crate) fn play(parameters: MatchParameters, events: &[GameEvent], frame_delay: Duration) {
+    let mut spectator = Spectator::new(parameters);
+    for evt in events {
+        if spectator.apply(evt) {
+            draw(&spectator);
+            std::thread::sleep(frame_delay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn spawn(id: &str, x: i32, y: i32) -> GameEvent {
+        GameEvent::MechSpawned {
+            mech: id.to_string(),
+            position: Point::new(x, y),
+            team: "earth".to_string(),
+            avatar: "none".to_string(),
+            name: id.to_string(),
+        }
+    }
+
+    #[test]
+    fn renders_shots_and_splash() {
+        let mut spectator = Spectator::new(MatchParameters {
+            match_id: "test".to_string(),
+            width: 8,
+            height: 8,
+            ..Default::default()
+        });
+        spectator.apply(&spawn("al", 1, 1));
+        spectator.apply(&spawn("bob", 1, 5));
+        spectator.apply(&GameEvent::WeaponFired {
+            mech: "al".to_string(),
+            weapon: WeaponType::Secondary,
+        });
+        spectator.apply(&GameEvent::DamageTaken {
+            damage_target: "bob".to_string(),
+            damage: 100,
+            damage_source: DamageSource::MechWeapon("al".to_string()),
+        });
+        let frame = spectator.apply(&GameEvent::MechTurnCompleted {
+            mech: "al".to_string(),
+            turn: 0,
+        });
+        assert!(frame);
+
+        let board = spectator.render();
+        let rows: Vec<&str> = board.lines().skip(2).take(8).collect();
+        // row 0 is the top of the board, y = 7
+        assert_eq!(rows[7 - 1], "| A      |");
+        assert_eq!(rows[7 - 3], "| .      |");
+        assert_eq!(rows[7 - 5], "|*X*     |");
+        assert_eq!(rows[7 - 6], "|***     |");
+        assert!(board.contains("bob took 100 damage from al"));
+    }
+
+    #[test]
+    fn resumes_from_match_state() {
+        let mut spectator = Spectator::new(MatchParameters {
+            match_id: "test".to_string(),
+            width: 8,
+            height: 8,
+            ..Default::default()
+        });
+        spectator.apply(&spawn("bob", 1, 5));
+        spectator.apply(&spawn("al", 1, 1));
+
+        let resumed = Spectator::resume(spectator.state().clone());
+        let board = resumed.render();
+        let rows: Vec<&str> = board.lines().skip(2).take(8).collect();
+        assert_eq!(rows.len(), 8);
+        assert_eq!(rows[7 - 1], "| A      |");
+        assert_eq!(rows[7 - 5], "| B      |");
+    }
+
+    #[test]
+    fn survives_events_out_of_order() {
+        let mut spectator = Spectator::new(MatchParameters {
+            match_id: "test".to_string(),
+            width: 8,
+            height: 8,
+            ..Default::default()
+        });
+        // events for a mech whose spawn was missed while joining
+        spectator.apply(&GameEvent::DamageTaken {
+            damage_target: "bob".to_string(),
+            damage: 10,
+            damage_source: DamageSource::MechWeapon("al".to_string()),
+        });
+        spectator.apply(&spawn("al", 1, 1));
+        spectator.apply(&spawn("al", 1, 1));
+
+        let board = spectator.render();
+        let rows: Vec<&str> = board.lines().skip(2).take(8).collect();
+        assert_eq!(rows[7 - 1], "| A      |");
+        assert_eq!(board.matches(" al ").count(), 1);
+    }
+}